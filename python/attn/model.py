@@ -5,13 +5,18 @@ import os
 from .vertex_attention import vertex_attention
 
 class Model(nn.Module):
-    def __init__(self, vertex_key_size=2, vertex_value_size=4, muscle_key_size=2, num_heads=20):
+    def __init__(self, vertex_key_size=2, vertex_value_size=4, muscle_key_size=2, num_heads=20,
+                 num_blocks=1, muscle_self_attention=False):
         super().__init__()
 
         self.vertex_key_size = vertex_key_size
         self.vertex_value_size = vertex_value_size
         self.muscle_key_size = muscle_key_size
         self.num_heads = num_heads
+        self.num_blocks = num_blocks
+        self.muscle_self_attention = muscle_self_attention
+
+        wv_size = num_heads * vertex_value_size
 
         self.muscle_k_to_vertex_q = nn.Sequential(
             nn.Linear(muscle_key_size, 32),
@@ -26,6 +31,24 @@ class Model(nn.Module):
             nn.Tanh()
         )
 
+        # Registered after wv_to_output so the single-block state_dict keeps
+        # its keys and order. Each extra block re-queries the vertices from
+        # the muscle key and what has been read so far (blocks.{i}.{0,2}.*).
+        self.blocks = nn.ModuleList([
+            nn.Sequential(
+                nn.Linear(wv_size + muscle_key_size, 32),
+                nn.ReLU(),
+                nn.Linear(32, vertex_key_size * num_heads),
+            )
+            for _ in range(num_blocks - 1)
+        ])
+
+        # Muscles attend to each other with the same multi-head attention
+        if muscle_self_attention:
+            self.muscle_self_q = nn.Linear(wv_size, vertex_key_size * num_heads)
+            self.muscle_self_k = nn.Linear(wv_size, vertex_key_size)
+            self.muscle_self_v = nn.Linear(wv_size, vertex_value_size)
+
     def forward(self, vertex_k, muscle_k, vertex_v, vertex_mask=None):
         batch_size, num_muscles, muscle_key_size = muscle_k.shape
         batch_size, num_vertices, vertex_key_size = vertex_k.shape
@@ -40,6 +63,19 @@ class Model(nn.Module):
         assert wv.shape == (batch_size, num_muscles, self.num_heads, vertex_value_size)
 
         wv = wv.view(batch_size, num_muscles, self.num_heads * vertex_value_size)
+
+        for block in self.blocks:
+            q = block(torch.cat([wv, muscle_k], dim=-1))
+            q = q.view(batch_size, num_muscles, self.num_heads, vertex_key_size)
+            wv1 = vertex_attention(vertex_k, vertex_v, q, mask=vertex_mask)
+            wv = wv + wv1.view(batch_size, num_muscles, self.num_heads * vertex_value_size)
+
+        if self.muscle_self_attention:
+            q = self.muscle_self_q(wv).view(batch_size, num_muscles, self.num_heads, vertex_key_size)
+            wv1 = vertex_attention(self.muscle_self_k(wv), self.muscle_self_v(wv), q)
+            assert wv1.shape == (batch_size, num_muscles, self.num_heads, vertex_value_size)
+            wv = wv + wv1.view(batch_size, num_muscles, self.num_heads * vertex_value_size)
+
         output = self.wv_to_output(wv)
         assert output.shape == (batch_size, num_muscles, 1)
         return output.squeeze(-1)
@@ -54,17 +90,30 @@ class Model(nn.Module):
             vertex_value_size = metadata.get("vertex_value_size")
             muscle_key_size = metadata.get("muscle_key_size")
             num_heads = metadata.get("num_heads")
+            num_blocks = metadata.get("num_blocks", 1)
+            muscle_self_attention = metadata.get("muscle_self_attention", False)
 
         model = Model(
             vertex_key_size=vertex_key_size,
             vertex_value_size=vertex_value_size,
             muscle_key_size=muscle_key_size,
-            num_heads=num_heads
+            num_heads=num_heads,
+            num_blocks=num_blocks,
+            muscle_self_attention=muscle_self_attention
         )
 
         model_filename = os.path.join(dirname, "model.pt")
+        weights_filename = os.path.join(dirname, "weights.json")
         if os.path.exists(model_filename):
             model.load_state_dict(torch.load(model_filename, map_location=device))
+        elif os.path.exists(weights_filename):
+            # weights.json as written by export_weights.py or AttentionModel::to_json
+            with open(weights_filename) as f:
+                weights = json.load(f)
+            model.load_state_dict({
+                key: torch.tensor(value, dtype=torch.float32, device=device)
+                for key, value in weights.items()
+            })
 
         return model
 
@@ -75,6 +124,11 @@ class Model(nn.Module):
             "muscle_key_size": self.muscle_key_size,
             "num_heads": self.num_heads
         }
+        # Only written for the deeper variants, so existing args.json stay as is
+        if self.num_blocks != 1:
+            metadata["num_blocks"] = self.num_blocks
+        if self.muscle_self_attention:
+            metadata["muscle_self_attention"] = True
 
         metadata_filename = os.path.join(dirname, "args.json")
         with open(metadata_filename, "w") as f:
@@ -0,0 +1,84 @@
+import argparse
+import json
+import struct
+
+# Converts between weights.json (nested lists, as written by export_weights.py)
+# and weights.bin (safetensors-style: u64 header length, JSON header, raw
+# little-endian f32 data), which the Rust AttentionModel::from_bin reads.
+
+def flatten(value):
+    if isinstance(value, list):
+        shape = [len(value)]
+        if len(value) == 0:
+            return shape, []
+        inner_shape, _ = flatten(value[0])
+        data = []
+        for item in value:
+            item_shape, item_data = flatten(item)
+            assert item_shape == inner_shape, "ragged tensor"
+            data.extend(item_data)
+        return shape + inner_shape, data
+    return [], [float(value)]
+
+def unflatten(shape, data):
+    if len(shape) == 0:
+        return data[0]
+    if len(shape) == 1:
+        return list(data)
+    stride = len(data) // shape[0]
+    return [unflatten(shape[1:], data[i * stride:(i + 1) * stride]) for i in range(shape[0])]
+
+def json_to_bin(weights):
+    header = {}
+    chunks = []
+    offset = 0
+    for name, value in weights.items():
+        shape, data = flatten(value)
+        chunk = struct.pack(f"<{len(data)}f", *data)
+        header[name] = {
+            "dtype": "F32",
+            "shape": shape,
+            "data_offsets": [offset, offset + len(chunk)]
+        }
+        chunks.append(chunk)
+        offset += len(chunk)
+
+    header_bytes = json.dumps(header, separators=(",", ":")).encode("utf-8")
+    header_bytes += b" " * ((8 - (8 + len(header_bytes)) % 8) % 8)
+    return struct.pack("<Q", len(header_bytes)) + header_bytes + b"".join(chunks)
+
+def bin_to_json(buf):
+    header_len, = struct.unpack_from("<Q", buf, 0)
+    header = json.loads(buf[8:8 + header_len].decode("utf-8"))
+    data_start = 8 + header_len
+
+    entries = [(name, info) for name, info in header.items() if name != "__metadata__"]
+    entries.sort(key=lambda entry: entry[1]["data_offsets"][0])
+
+    weights = {}
+    for name, info in entries:
+        assert info["dtype"] == "F32", f"unsupported dtype {info['dtype']}"
+        begin, end = info["data_offsets"]
+        count = (end - begin) // 4
+        data = struct.unpack_from(f"<{count}f", buf, data_start + begin)
+        weights[name] = unflatten(info["shape"], data)
+    return weights
+
+if __name__ == "__main__":
+    arg_parser = argparse.ArgumentParser()
+    arg_parser.add_argument("input", type=str)
+    arg_parser.add_argument("output", type=str)
+    args = arg_parser.parse_args()
+
+    if args.input.endswith(".json"):
+        with open(args.input) as f:
+            weights = json.load(f)
+        with open(args.output, "wb") as f:
+            f.write(json_to_bin(weights))
+    else:
+        with open(args.input, "rb") as f:
+            weights = bin_to_json(f.read())
+        with open(args.output, "w") as f:
+            json.dump(weights, f)
+
+    print(f"Converted {args.input} to {args.output}")
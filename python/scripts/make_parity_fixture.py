@@ -0,0 +1,70 @@
+import argparse
+import json
+import os
+import shutil
+import subprocess
+import sys
+import tempfile
+from pathlib import Path
+
+this_dirpath = Path(os.path.realpath(__file__)).parent
+
+# Writes a small trajectory fixture for the Rust parity tests
+# (rust/components/trajectory.rs), in the same layout as
+# generate_trajectory_with_attn_policy.py: mesh.json + steps/N.json.
+# Every step comes from attn.Model and an algovivo step, so it carries the
+# next state (pos1, vel1, a1) as well as policy_output.
+#
+# --trajectory DIR   trims a trajectory produced by the generator script
+# --agent/--policy   runs the generator first (needs torch, algovivo and
+#                    ALGOVIVO_NATIVE_LIB_FILENAME), then trims its output
+
+STEP_KEYS = ["pos0", "vel0", "a0", "policy_output", "pos1", "vel1", "a1"]
+
+def generate(agent, policy, num_steps, output_dirpath):
+    cmd = [
+        sys.executable, str(this_dirpath.joinpath("generate_trajectory_with_attn_policy.py")),
+        "--agent", agent,
+        "--policy", policy,
+        "--steps", str(num_steps),
+        "--output", str(output_dirpath)
+    ]
+    subprocess.run(cmd, check=True)
+
+def trim(trajectory_dirpath, output_dirpath, first_step, num_steps):
+    steps_dirpath = output_dirpath.joinpath("steps")
+    os.makedirs(steps_dirpath, exist_ok=True)
+    shutil.copy(trajectory_dirpath.joinpath("mesh.json"), output_dirpath.joinpath("mesh.json"))
+    for i in range(num_steps):
+        with open(trajectory_dirpath.joinpath("steps", f"{first_step + i}.json")) as f:
+            step = json.load(f)
+        missing = [key for key in STEP_KEYS if key not in step]
+        if missing:
+            raise ValueError(f"step {first_step + i} has no {', '.join(missing)}; not a generator trajectory")
+        with open(steps_dirpath.joinpath(f"{i}.json"), "w") as f:
+            json.dump({key: step[key] for key in STEP_KEYS}, f)
+
+if __name__ == "__main__":
+    arg_parser = argparse.ArgumentParser()
+    arg_parser.add_argument("--trajectory", type=str)
+    arg_parser.add_argument("--agent", type=str)
+    arg_parser.add_argument("--policy", type=str, default="data/policies/attn")
+    arg_parser.add_argument("--steps", type=int, default=3)
+    # skip the first steps, where the creature is still settling from rest
+    arg_parser.add_argument("--first-step", type=int, default=10)
+    arg_parser.add_argument("--output", "-o", type=str, required=True)
+    args = arg_parser.parse_args()
+
+    output_dirpath = Path(args.output)
+    shutil.rmtree(output_dirpath, ignore_errors=True)
+
+    if args.trajectory is not None:
+        trim(Path(args.trajectory), output_dirpath, args.first_step, args.steps)
+    else:
+        assert args.agent is not None, "either --trajectory or --agent must be provided"
+        with tempfile.TemporaryDirectory() as tmp_dirname:
+            trajectory_dirpath = Path(tmp_dirname).joinpath("trajectory")
+            generate(args.agent, args.policy, args.first_step + args.steps, trajectory_dirpath)
+            trim(trajectory_dirpath, output_dirpath, args.first_step, args.steps)
+
+    print(f"fixture saved to {output_dirpath}")
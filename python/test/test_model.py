@@ -1,4 +1,5 @@
 import attn
+import json
 import torch
 torch.manual_seed(0)
 
@@ -47,4 +48,38 @@ def test_model_shapes_with_different_key_sizes():
     muscle_k = torch.randn(batch_size, num_muscles, muscle_key_size)
 
     da = model(vertex_k, muscle_k, vertex_v)
-    assert da.shape == (batch_size, num_muscles)
\ No newline at end of file
+    assert da.shape == (batch_size, num_muscles)
+
+def test_load_from_weights_json(tmp_path):
+    model = attn.Model()
+    model.save(tmp_path)
+    weights = {key: value.tolist() for key, value in model.state_dict().items()}
+    (tmp_path / "model.pt").unlink()
+    with open(tmp_path / "weights.json", "w") as f:
+        json.dump(weights, f)
+
+    loaded = attn.Model.load(tmp_path)
+    for key, value in model.state_dict().items():
+        assert torch.equal(loaded.state_dict()[key], value)
+
+
+def test_model_variants(tmp_path):
+    batch_size = 3
+    num_muscles = 5
+    num_vertices = 8
+
+    model = attn.Model(num_blocks=3, muscle_self_attention=True)
+    assert list(model.state_dict().keys())[:8] == list(attn.Model().state_dict().keys())
+    assert "blocks.1.2.weight" in model.state_dict()
+    assert "muscle_self_q.weight" in model.state_dict()
+
+    vertex_k = torch.randn(batch_size, num_vertices, 2)
+    vertex_v = torch.randn(batch_size, num_vertices, 4)
+    muscle_k = torch.randn(batch_size, num_muscles, 2)
+    da = model(vertex_k, muscle_k, vertex_v)
+    assert da.shape == (batch_size, num_muscles)
+
+    model.save(tmp_path)
+    loaded = attn.Model.load(tmp_path)
+    assert loaded.num_blocks == 3 and loaded.muscle_self_attention
+    assert torch.equal(loaded(vertex_k, muscle_k, vertex_v), da)
@@ -0,0 +1,101 @@
+// Behavior cloning of the attention policy from recorded trajectories.
+//
+//   cargo run --release --bin train_bc -- --trajectories data/trajectories/biped_attn \
+//       --agent data/agents/biped --policy data/policies/attn --output bc.out --epochs 100
+//
+// --trajectories takes a comma-separated list of trajectory directories (each
+// with mesh.json and steps/N.json). Training starts from --policy's weights and
+// writes <output>/args.json, <output>/weights.json and <output>/weights.bin
+// after every epoch.
+
+use morphology_adaptive::components::bc::{samples_from_trajectory, BcConfig, BcTrainer};
+use morphology_adaptive::components::observation::{ObservationBuilder, PolicyMetadata};
+use morphology_adaptive::components::policy::AttentionModel;
+use morphology_adaptive::components::trajectory::Trajectory;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const USAGE: &str = "usage: train_bc [--trajectories DIR[,DIR...]] [--agent DIR] [--policy DIR] [--output DIR] \
+[--epochs N] [--batch-size N] [--lr X] [--seed N]";
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// Every flag in USAGE; anything else is a typo, not an option to ignore
+const FLAGS: [&str; 8] = ["trajectories", "agent", "policy", "output", "epochs", "batch-size", "lr", "seed"];
+
+// `--name value` pairs, checked against FLAGS
+fn parse_args(argv: &[String]) -> Result<HashMap<&str, &str>, String> {
+    let mut args = HashMap::new();
+    let mut rest = argv.iter();
+    while let Some(flag) = rest.next() {
+        let name = flag.strip_prefix("--").filter(|name| FLAGS.contains(name)).ok_or_else(|| format!("unknown argument {:?}", flag))?;
+        let value = rest.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        args.insert(name, value.as_str());
+    }
+    Ok(args)
+}
+
+fn parse<T: std::str::FromStr>(args: &HashMap<&str, &str>, name: &str, default: T) -> Result<T, String> {
+    match args.get(name) {
+        Some(value) => value.parse().map_err(|_| format!("--{}: cannot parse {:?}", name, value)),
+        None => Ok(default),
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}\n{}", err, USAGE);
+        std::process::exit(2);
+    }
+}
+
+fn run() -> Result<(), String> {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let args = parse_args(&argv)?;
+    let arg = |name: &str, default: &str| args.get(name).copied().unwrap_or(default).to_string();
+
+    let trajectories = arg("trajectories", "data/trajectories/biped_attn");
+    let agent = arg("agent", "data/agents/biped");
+    let policy = arg("policy", "data/policies/attn");
+    let output = arg("output", "bc.out");
+    let epochs: usize = parse(&args, "epochs", 100)?;
+
+    let defaults = BcConfig::default();
+    let config = BcConfig {
+        batch_size: parse(&args, "batch-size", defaults.batch_size)?,
+        learning_rate: parse(&args, "lr", defaults.learning_rate)?,
+        seed: parse(&args, "seed", defaults.seed)?,
+    };
+
+    let meta = PolicyMetadata::from_json(&read(&Path::new(&agent).join("policy.json"))?)?;
+    let mut samples = Vec::new();
+    for dirname in trajectories.split(',') {
+        let trajectory = Trajectory::load_dir(dirname)?;
+        let builder = ObservationBuilder::new(&trajectory.mesh, meta.center_vertex_id, meta.forward_vertex_id);
+        samples.extend(samples_from_trajectory(&trajectory, &builder));
+    }
+    println!("{} samples", samples.len());
+
+    let args_json = read(&Path::new(&policy).join("args.json"))?;
+    let weights_json = read(&Path::new(&policy).join("weights.json"))?;
+    let model = AttentionModel::try_new(&args_json, &weights_json)?;
+    let mut trainer = BcTrainer::new(model, config)?;
+
+    let write = |name: &str, contents: &[u8]| fs::write(Path::new(&output).join(name), contents).map_err(|e| format!("{}: {}", output, e));
+    fs::create_dir_all(&output).map_err(|e| format!("{}: {}", output, e))?;
+    for epoch in 0..epochs {
+        let loss = trainer.epoch(&samples);
+        println!("epoch {}: loss {:.6}", epoch, loss);
+
+        let (args_json, weights_json) = trainer.model().to_json();
+        write("args.json", args_json.as_bytes())?;
+        write("weights.json", weights_json.as_bytes())?;
+        write("weights.bin", &trainer.model().weights_bin())?;
+    }
+
+    println!("weights saved to {}", output);
+    Ok(())
+}
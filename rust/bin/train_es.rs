@@ -0,0 +1,102 @@
+// Native evolution-strategies trainer for the attention policy.
+//
+//   cargo run --release --bin train_es -- --agent data/agents/biped \
+//       --policy data/policies/attn --output es.out --generations 50 --seed 0
+//
+// Writes <output>/args.json, then after every generation <output>/weights_last.json
+// and, whenever the updated weights beat every earlier score (the starting
+// policy's included), <output>/weights.json.
+
+use morphology_adaptive::components::es::{rollout_score, EsConfig, EsTrainer};
+use morphology_adaptive::components::policy::AttentionModel;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const USAGE: &str = "usage: train_es [--agent DIR] [--policy DIR] [--output DIR] [--generations N] \
+[--population N] [--sigma X] [--lr X] [--steps N] [--seed N]";
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// Every flag in USAGE; anything else is a typo, not an option to ignore
+const FLAGS: [&str; 9] = ["agent", "policy", "output", "generations", "population", "sigma", "lr", "steps", "seed"];
+
+// `--name value` pairs, checked against FLAGS
+fn parse_args(argv: &[String]) -> Result<HashMap<&str, &str>, String> {
+    let mut args = HashMap::new();
+    let mut rest = argv.iter();
+    while let Some(flag) = rest.next() {
+        let name = flag.strip_prefix("--").filter(|name| FLAGS.contains(name)).ok_or_else(|| format!("unknown argument {:?}", flag))?;
+        let value = rest.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        args.insert(name, value.as_str());
+    }
+    Ok(args)
+}
+
+fn parse<T: std::str::FromStr>(args: &HashMap<&str, &str>, name: &str, default: T) -> Result<T, String> {
+    match args.get(name) {
+        Some(value) => value.parse().map_err(|_| format!("--{}: cannot parse {:?}", name, value)),
+        None => Ok(default),
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}\n{}", err, USAGE);
+        std::process::exit(2);
+    }
+}
+
+fn run() -> Result<(), String> {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let args = parse_args(&argv)?;
+    let arg = |name: &str, default: &str| args.get(name).copied().unwrap_or(default).to_string();
+
+    let agent = arg("agent", "data/agents/biped");
+    let policy = arg("policy", "data/policies/attn");
+    let output = arg("output", "es.out");
+    let generations: usize = parse(&args, "generations", 50)?;
+
+    let defaults = EsConfig::default();
+    let config = EsConfig {
+        population: parse(&args, "population", defaults.population)?,
+        sigma: parse(&args, "sigma", defaults.sigma)?,
+        learning_rate: parse(&args, "lr", defaults.learning_rate)?,
+        rollout_steps: parse(&args, "steps", defaults.rollout_steps)?,
+        seed: parse(&args, "seed", defaults.seed)?,
+    };
+
+    let args_json = read(&Path::new(&policy).join("args.json"))?;
+    let weights_json = read(&Path::new(&policy).join("weights.json"))?;
+    let mesh_json = read(&Path::new(&agent).join("mesh.json"))?;
+    let policy_json = read(&Path::new(&agent).join("policy.json"))?;
+
+    let model = AttentionModel::try_new(&args_json, &weights_json)?;
+    let mut best_score = rollout_score(&model, &mesh_json, &policy_json, config.rollout_steps)?;
+    println!("starting policy: x displacement {:.3}", best_score);
+    let mut trainer = EsTrainer::new(model, &mesh_json, &policy_json, config)?;
+
+    let write = |name: &str, contents: &str| fs::write(Path::new(&output).join(name), contents).map_err(|e| format!("{}: {}", output, e));
+    fs::create_dir_all(&output).map_err(|e| format!("{}: {}", output, e))?;
+    write("args.json", &args_json)?;
+    write("weights.json", &weights_json)?;
+
+    for _ in 0..generations {
+        let generation = trainer.step()?;
+        println!(
+            "generation {}: x displacement {:.3} (mean {:.3}, best {:.3})",
+            generation.index, generation.score, generation.mean_score, generation.best_score
+        );
+        let weights_json = trainer.model().weights_json();
+        write("weights_last.json", &weights_json)?;
+        if generation.score > best_score {
+            best_score = generation.score;
+            write("weights.json", &weights_json)?;
+        }
+    }
+
+    println!("best x displacement {:.3}, weights saved to {}", best_score, output);
+    Ok(())
+}
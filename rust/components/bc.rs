@@ -0,0 +1,333 @@
+// Behavior cloning: fits an AttentionModel to recorded (observation,
+// policy_output) pairs by mean squared error, with backprop written out by
+// hand through both MLPs and the per-head softmax attention, and Adam.
+
+use crate::components::observation::ObservationBuilder;
+use crate::components::policy::{AttentionModel, PolicyInput, HIDDEN_SIZE};
+use crate::components::rng::Rng;
+use crate::components::trajectory::Trajectory;
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub input: PolicyInput,
+    pub target: Vec<f32>,
+}
+
+pub fn samples_from_trajectory(trajectory: &Trajectory, builder: &ObservationBuilder) -> Vec<Sample> {
+    trajectory
+        .observations(builder)
+        .into_iter()
+        .zip(trajectory.steps.iter())
+        .map(|(input, step)| Sample { input, target: step.policy_output.clone() })
+        .collect()
+}
+
+// Offsets of each parameter in the flat parameters() vector. Covers the
+// single-block model only; the gradient below does not know the variants.
+struct Offsets {
+    w1: usize,
+    b1: usize,
+    w2: usize,
+    b2: usize,
+    w3: usize,
+    b3: usize,
+    w4: usize,
+    b4: usize,
+}
+
+impl Offsets {
+    fn new(model: &AttentionModel) -> Self {
+        let mut offsets = [0; 8];
+        let mut offset = 0;
+        for (i, (_, shape)) in model.parameter_shapes().iter().take(8).enumerate() {
+            offsets[i] = offset;
+            offset += shape.iter().product::<usize>();
+        }
+        let [w1, b1, w2, b2, w3, b3, w4, b4] = offsets;
+        Self { w1, b1, w2, b2, w3, b3, w4, b4 }
+    }
+}
+
+fn linear(params: &[f32], w: usize, b: usize, input: &[f32], out_features: usize) -> Vec<f32> {
+    let n = input.len();
+    (0..out_features)
+        .map(|i| params[b + i] + (0..n).map(|j| params[w + i * n + j] * input[j]).sum::<f32>())
+        .collect()
+}
+
+// Accumulates d(loss)/d(params) into `grad` for one weight row layout:
+// dW += d_out x input, db += d_out. Returns W^T d_out.
+fn linear_backward(params: &[f32], grad: &mut [f32], w: usize, b: usize, input: &[f32], d_out: &[f32]) -> Vec<f32> {
+    let n = input.len();
+    let mut d_input = vec![0.0; n];
+    for (i, &d) in d_out.iter().enumerate() {
+        grad[b + i] += d;
+        for j in 0..n {
+            grad[w + i * n + j] += d * input[j];
+            d_input[j] += d * params[w + i * n + j];
+        }
+    }
+    d_input
+}
+
+// Mean squared error over the sample's muscles; adds its gradient (scaled by
+// `scale`) into `grad`, laid out like AttentionModel::parameters().
+pub fn loss_and_gradient(model: &AttentionModel, params: &[f32], sample: &Sample, scale: f32, grad: &mut [f32]) -> f32 {
+    let o = Offsets::new(model);
+    let dims = model.dims();
+    let (dk, dv, heads) = (dims.vertex_key_size, dims.vertex_value_size, dims.num_heads);
+    let input = &sample.input;
+    let num_muscles = input.num_muscles;
+    let mut loss = 0.0;
+
+    for m in 0..num_muscles {
+        // Forward, keeping what the backward pass needs
+        let m_k = &input.muscle_k[m * dims.muscle_key_size..(m + 1) * dims.muscle_key_size];
+        let x1 = linear(params, o.w1, o.b1, m_k, HIDDEN_SIZE);
+        let h1: Vec<f32> = x1.iter().map(|x| x.max(0.0)).collect();
+        let q = linear(params, o.w2, o.b2, &h1, heads * dk);
+
+        let mut weights = vec![0.0; heads * input.num_vertices];
+        let mut wv = vec![0.0; heads * dv];
+        for h in 0..heads {
+            let q_h = &q[h * dk..(h + 1) * dk];
+            let w = &mut weights[h * input.num_vertices..(h + 1) * input.num_vertices];
+            for (v, w) in w.iter_mut().enumerate() {
+                *w = (0..dk).map(|i| q_h[i] * input.vertex_k[v * dk + i]).sum();
+            }
+            let max = w.iter().fold(f32::NEG_INFINITY, |a, &b| a.max(b));
+            let mut sum = 0.0;
+            for w in w.iter_mut() {
+                *w = (*w - max).exp();
+                sum += *w;
+            }
+            for (v, w) in w.iter_mut().enumerate() {
+                *w /= sum;
+                for i in 0..dv {
+                    wv[h * dv + i] += *w * input.vertex_v[v * dv + i];
+                }
+            }
+        }
+
+        let y1 = linear(params, o.w3, o.b3, &wv, HIDDEN_SIZE);
+        let h3: Vec<f32> = y1.iter().map(|y| y.max(0.0)).collect();
+        let out = linear(params, o.w4, o.b4, &h3, 1)[0].tanh();
+
+        let err = out - sample.target[m];
+        loss += err * err / num_muscles as f32;
+
+        // Backward
+        let d_out = scale * 2.0 * err / num_muscles as f32;
+        let dz = d_out * (1.0 - out * out);
+        let d_h3 = linear_backward(params, grad, o.w4, o.b4, &h3, &[dz]);
+        let d_y1: Vec<f32> = d_h3.iter().zip(&y1).map(|(d, &y)| if y > 0.0 { *d } else { 0.0 }).collect();
+        let d_wv = linear_backward(params, grad, o.w3, o.b3, &wv, &d_y1);
+
+        let mut d_q = vec![0.0; heads * dk];
+        for h in 0..heads {
+            let w = &weights[h * input.num_vertices..(h + 1) * input.num_vertices];
+            let d_wv_h = &d_wv[h * dv..(h + 1) * dv];
+            // d(loss)/d(attention weight of vertex v), then through the softmax
+            let d_w: Vec<f32> = (0..input.num_vertices)
+                .map(|v| (0..dv).map(|i| d_wv_h[i] * input.vertex_v[v * dv + i]).sum())
+                .collect();
+            let dot: f32 = w.iter().zip(&d_w).map(|(w, d)| w * d).sum();
+            for v in 0..input.num_vertices {
+                let d_score = w[v] * (d_w[v] - dot);
+                for i in 0..dk {
+                    d_q[h * dk + i] += d_score * input.vertex_k[v * dk + i];
+                }
+            }
+        }
+
+        let d_h1 = linear_backward(params, grad, o.w2, o.b2, &h1, &d_q);
+        let d_x1: Vec<f32> = d_h1.iter().zip(&x1).map(|(d, &x)| if x > 0.0 { *d } else { 0.0 }).collect();
+        linear_backward(params, grad, o.w1, o.b1, m_k, &d_x1);
+    }
+
+    loss
+}
+
+#[derive(Debug, Clone)]
+pub struct Adam {
+    pub learning_rate: f32,
+    pub beta1: f32,
+    pub beta2: f32,
+    pub eps: f32,
+    m: Vec<f32>,
+    v: Vec<f32>,
+    t: i32,
+}
+
+impl Adam {
+    // torch.optim.Adam defaults
+    pub fn new(num_params: usize, learning_rate: f32) -> Self {
+        Self {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            eps: 1e-8,
+            m: vec![0.0; num_params],
+            v: vec![0.0; num_params],
+            t: 0,
+        }
+    }
+
+    pub fn step(&mut self, params: &mut [f32], grad: &[f32]) {
+        self.t += 1;
+        let correction1 = 1.0 - self.beta1.powi(self.t);
+        let correction2 = 1.0 - self.beta2.powi(self.t);
+        for i in 0..params.len() {
+            self.m[i] = self.beta1 * self.m[i] + (1.0 - self.beta1) * grad[i];
+            self.v[i] = self.beta2 * self.v[i] + (1.0 - self.beta2) * grad[i] * grad[i];
+            let m_hat = self.m[i] / correction1;
+            let v_hat = self.v[i] / correction2;
+            params[i] -= self.learning_rate * m_hat / (v_hat.sqrt() + self.eps);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BcConfig {
+    pub batch_size: usize,
+    pub learning_rate: f32,
+    pub seed: u64, // shuffling order
+}
+
+impl Default for BcConfig {
+    fn default() -> Self {
+        Self { batch_size: 32, learning_rate: 1e-3, seed: 0 }
+    }
+}
+
+pub struct BcTrainer {
+    pub config: BcConfig,
+    model: AttentionModel,
+    params: Vec<f32>,
+    adam: Adam,
+    rng: Rng,
+}
+
+impl BcTrainer {
+    pub fn new(model: AttentionModel, config: BcConfig) -> Result<Self, String> {
+        if model.num_blocks != 1 || model.muscle_self_attention {
+            return Err("behavior cloning supports the single-block model only".to_string());
+        }
+        let params = model.parameters();
+        Ok(Self {
+            adam: Adam::new(params.len(), config.learning_rate),
+            rng: Rng::new(config.seed),
+            params,
+            model,
+            config,
+        })
+    }
+
+    // Mean loss over `samples` without training
+    pub fn loss(&self, samples: &[Sample]) -> f32 {
+        let mut grad = vec![0.0; self.params.len()];
+        let total: f32 = samples.iter().map(|s| loss_and_gradient(&self.model, &self.params, s, 0.0, &mut grad)).sum();
+        total / samples.len().max(1) as f32
+    }
+
+    // One shuffled pass over `samples`; returns the mean training loss
+    pub fn epoch(&mut self, samples: &[Sample]) -> f32 {
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        for i in (1..order.len()).rev() {
+            let j = (self.rng.next_u64() % (i as u64 + 1)) as usize;
+            order.swap(i, j);
+        }
+
+        let mut grad = vec![0.0; self.params.len()];
+        let mut total = 0.0;
+        for batch in order.chunks(self.config.batch_size.max(1)) {
+            grad.iter_mut().for_each(|g| *g = 0.0);
+            let scale = 1.0 / batch.len() as f32;
+            for &i in batch {
+                total += loss_and_gradient(&self.model, &self.params, &samples[i], scale, &mut grad);
+            }
+            self.adam.step(&mut self.params, &grad);
+        }
+        self.model.set_parameters(&self.params);
+        total / samples.len().max(1) as f32
+    }
+
+    pub fn model(&self) -> &AttentionModel {
+        &self.model
+    }
+
+    pub fn into_model(self) -> AttentionModel {
+        self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::observation::PolicyMetadata;
+
+    const ARGS_JSON: &str = include_str!("../../data/policies/attn/args.json");
+    const WEIGHTS_JSON: &str = include_str!("../../data/policies/attn/weights.json");
+    const POLICY_JSON: &str = include_str!("../../data/agents/biped/policy.json");
+    const FIXTURE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/trajectories/biped_attn");
+
+    fn fixture_samples() -> Vec<Sample> {
+        let trajectory = Trajectory::load_dir(FIXTURE_DIR).unwrap();
+        let meta = PolicyMetadata::from_json(POLICY_JSON).unwrap();
+        let builder = ObservationBuilder::new(&trajectory.mesh, meta.center_vertex_id, meta.forward_vertex_id);
+        samples_from_trajectory(&trajectory, &builder)
+    }
+
+    // A model the fixture's outputs do not already fit
+    fn perturbed_model() -> AttentionModel {
+        let mut model = AttentionModel::try_new(ARGS_JSON, WEIGHTS_JSON).unwrap();
+        let mut rng = Rng::new(3);
+        let params: Vec<f32> = model.parameters().iter().map(|p| p + 0.1 * rng.gaussian()).collect();
+        model.set_parameters(&params);
+        model
+    }
+
+    #[test]
+    fn test_gradient_matches_finite_differences() {
+        let model = perturbed_model();
+        let sample = &fixture_samples()[0];
+        let params = model.parameters();
+        let mut grad = vec![0.0; params.len()];
+        loss_and_gradient(&model, &params, sample, 1.0, &mut grad);
+
+        // One entry of every parameter tensor
+        let mut offset = 0;
+        for (name, shape) in model.parameter_shapes() {
+            let i = offset + shape.iter().product::<usize>() / 2;
+            offset += shape.iter().product::<usize>();
+
+            let h = 1e-3;
+            let mut scratch = vec![0.0; params.len()];
+            let mut shifted = params.clone();
+            shifted[i] = params[i] + h;
+            let plus = loss_and_gradient(&model, &shifted, sample, 0.0, &mut scratch);
+            shifted[i] = params[i] - h;
+            let minus = loss_and_gradient(&model, &shifted, sample, 0.0, &mut scratch);
+
+            let numeric = (plus - minus) / (2.0 * h);
+            assert!((numeric - grad[i]).abs() < 1e-3 + 0.05 * grad[i].abs(), "{}: {} vs {}", name, numeric, grad[i]);
+        }
+    }
+
+    #[test]
+    fn test_fits_recorded_outputs() {
+        let samples = fixture_samples();
+        let mut trainer = BcTrainer::new(perturbed_model(), BcConfig { batch_size: 2, ..BcConfig::default() }).unwrap();
+        let before = trainer.loss(&samples);
+        for _ in 0..50 {
+            trainer.epoch(&samples);
+        }
+        let after = trainer.loss(&samples);
+        assert!(after < 0.5 * before, "{} -> {}", before, after);
+
+        // The result round-trips through the standard weights format
+        let (args_json, weights_json) = trainer.model().to_json();
+        let reloaded = AttentionModel::try_new(&args_json, &weights_json).unwrap();
+        assert_eq!(reloaded.parameters(), trainer.model().parameters());
+    }
+}
@@ -0,0 +1,155 @@
+use wasm_bindgen::prelude::*;
+use crate::components::clock::TICK_RATE;
+use crate::components::creature::Morphology;
+use crate::components::input::InputState;
+use crate::components::racer::{Racer, MORPH_TIME};
+use crate::components::stamina::{StaminaRates, TIRED_BELOW};
+use crate::components::track::Track;
+
+// A paced bot eases off to cruise this far above the point where its
+// muscles start to tire
+const PACE_MARGIN: f32 = 0.05;
+
+// Off-center less than this, the bot leaves the steering alone
+const STEER_SLACK: f32 = 0.05;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy = 0,
+    Medium = 1,
+    Hard = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BotConfig {
+    pub reaction: f32,             // seconds after the go before it moves
+    pub throttle: f32,             // the most it ever gives
+    pub pacing: bool,              // cruises rather than tire itself out
+    pub sprint: bool,              // flat out once it can last to the line
+    pub anticipation: Option<f32>, // morphs for the terrain this many seconds ahead; None ignores terrain
+}
+
+impl BotConfig {
+    pub fn for_difficulty(difficulty: Difficulty) -> Self {
+        match difficulty {
+            Difficulty::Easy => Self { reaction: 0.6, throttle: 0.8, pacing: false, sprint: false, anticipation: None },
+            Difficulty::Medium => Self { reaction: 0.35, throttle: 0.95, pacing: true, sprint: false, anticipation: Some(0.0) },
+            Difficulty::Hard => Self { reaction: 0.15, throttle: 1.0, pacing: true, sprint: true, anticipation: Some(MORPH_TIME) },
+        }
+    }
+}
+
+// What the bot does this tick: the same input a player would give, plus the
+// form it wants, which it picks directly rather than cycling the morph key
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BotAction {
+    pub input: InputState,
+    pub morph: Option<Morphology>,
+}
+
+// Drives one racer slot with a heuristic throttle. In physics races the
+// racer's policy (an attention model from load_policy_for, or the CPG)
+// still moves the body; the bot only decides how hard it runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bot {
+    pub difficulty: Difficulty,
+    pub config: BotConfig,
+    since_go: u64, // ticks
+}
+
+impl Bot {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self { difficulty, config: BotConfig::for_difficulty(difficulty), since_go: 0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.since_go = 0;
+    }
+
+    // One racing tick. `remaining` is how far the racer still has to go.
+    pub fn decide(&mut self, racer: &Racer, track: &Track, remaining: f32) -> BotAction {
+        self.since_go += 1;
+        if self.since_go <= (self.config.reaction * TICK_RATE as f32).round() as u64 {
+            return BotAction::default();
+        }
+
+        let mut throttle = self.config.throttle;
+        let rates = StaminaRates::for_morphology(racer.creature.morphology);
+        if self.config.pacing && racer.stamina.level < TIRED_BELOW + PACE_MARGIN {
+            // The effort at which drain and recovery cancel out
+            throttle = throttle.min(rates.recovery / (rates.recovery + rates.drain));
+        }
+        if self.config.sprint {
+            let time_left = remaining / racer.speed.max(1.0);
+            if racer.stamina.level > rates.drain * time_left {
+                throttle = self.config.throttle;
+            }
+        }
+
+        let steer = if racer.lateral.abs() > STEER_SLACK { (racer.lateral / racer.lane_width * 4.0).clamp(-1.0, 1.0) } else { 0.0 };
+
+        let morph = self.config.anticipation.and_then(|ahead| {
+            let wanted = track.terrain_at(racer.laps.distance + racer.speed.max(0.0) * ahead).favors()?;
+            (wanted != racer.creature.morphology && racer.can_morph()).then_some(wanted)
+        });
+
+        BotAction { input: InputState { throttle, brake: 0.0, steer, morph: false }, morph }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::terrain::Terrain;
+
+    #[test]
+    fn test_reaction_and_pacing() {
+        let track = Track::straight();
+        let mut racer = Racer::new(0, 1, Morphology::Biped);
+        let mut bot = Bot::new(Difficulty::Medium);
+        let mut ticks = 0;
+        while bot.decide(&racer, &track, 30.0).input.throttle == 0.0 {
+            ticks += 1;
+        }
+        assert_eq!(ticks, 21);
+
+        racer.stamina.level = TIRED_BELOW;
+        let cruise = bot.decide(&racer, &track, 30.0).input.throttle;
+        assert!(cruise > 0.0 && cruise < 0.95);
+
+        // Hard bots hold back too, but not on the run-in
+        let mut hard = Bot::new(Difficulty::Hard);
+        hard.since_go = 60;
+        racer.speed = 5.0;
+        assert!(hard.decide(&racer, &track, 30.0).input.throttle < 1.0);
+        assert_eq!(hard.decide(&racer, &track, 0.5).input.throttle, 1.0);
+
+        // Drifted left, it steers right
+        racer.lateral = 1.0;
+        assert!(bot.decide(&racer, &track, 30.0).input.steer > 0.0);
+    }
+
+    #[test]
+    fn test_morphs_for_terrain() {
+        let mut track = Track::straight();
+        track.terrain = vec![(10.0, 20.0, Terrain::Sand)];
+        let mut racer = Racer::new(0, 1, Morphology::Biped);
+        racer.speed = 5.0;
+        racer.laps.distance = 7.0;
+
+        let mut medium = Bot::new(Difficulty::Medium);
+        let mut hard = Bot::new(Difficulty::Hard);
+        let mut easy = Bot::new(Difficulty::Easy);
+        for bot in [&mut medium, &mut hard, &mut easy] {
+            bot.since_go = 60;
+        }
+        // Only the hard bot sees the sand coming
+        assert_eq!(hard.decide(&racer, &track, 30.0).morph, Some(Morphology::Hexapod));
+        assert_eq!(medium.decide(&racer, &track, 30.0).morph, None);
+
+        racer.laps.distance = 12.0;
+        assert_eq!(medium.decide(&racer, &track, 30.0).morph, Some(Morphology::Hexapod));
+        assert_eq!(easy.decide(&racer, &track, 30.0).morph, None);
+    }
+}
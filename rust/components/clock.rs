@@ -0,0 +1,69 @@
+// Fixed-step race clock. The host passes in its own timestamps (Date.now()
+// in the browser, anything in tests); the clock turns them into whole ticks
+// to simulate, and race time is counted in ticks, never read off the wall.
+
+pub const TICK_RATE: u32 = 60;
+pub const TICK_DT: f32 = 1.0 / TICK_RATE as f32;
+
+// After a stall (a background tab, a breakpoint) only this many ticks are
+// caught up; the rest of the gap is dropped rather than simulated
+pub const MAX_CATCH_UP_TICKS: u64 = 8;
+
+// Timestamps a hair short of a tick boundary still reach it
+const TICK_EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TickClock {
+    pub tick: u64,
+    origin: f64, // host time, in ms, at which tick 0 started
+}
+
+impl TickClock {
+    pub fn start(now: f64) -> Self {
+        Self { tick: 0, origin: now }
+    }
+
+    // Ticks due by host time `now`, in ms; the caller runs that many
+    pub fn advance(&mut self, now: f64) -> u64 {
+        let elapsed = (now - self.origin) / 1000.0 * TICK_RATE as f64;
+        let due = (elapsed + TICK_EPSILON).floor().max(0.0) as u64;
+        let ticks = due.saturating_sub(self.tick);
+        if ticks > MAX_CATCH_UP_TICKS {
+            // Move the origin so the dropped time is never owed again
+            self.origin += (ticks - MAX_CATCH_UP_TICKS) as f64 * 1000.0 / TICK_RATE as f64;
+            return MAX_CATCH_UP_TICKS;
+        }
+        ticks
+    }
+
+    // Race time of `tick`, in seconds
+    pub fn seconds(tick: u64) -> f64 {
+        tick as f64 / TICK_RATE as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_to_ticks() {
+        let mut clock = TickClock::start(1000.0);
+        assert_eq!(clock.advance(1000.0), 0);
+        // Uneven frames still add up to one tick per 1/60 s
+        let mut total = 0;
+        for now in [1010.0, 1040.0, 1050.0, 1100.0] {
+            let ticks = clock.advance(now);
+            clock.tick += ticks;
+            total += ticks;
+        }
+        assert_eq!(total, 6);
+        assert_eq!(clock.advance(1000.0 + 7000.0 / 60.0), 1);
+
+        // A stall is capped and not owed afterwards
+        assert_eq!(clock.advance(60_000.0), MAX_CATCH_UP_TICKS);
+        clock.tick += MAX_CATCH_UP_TICKS;
+        assert_eq!(clock.advance(60_000.0), 0);
+        assert_eq!(clock.advance(60_000.0 + 1000.0 / 60.0), 1);
+    }
+}
@@ -0,0 +1,397 @@
+// Per-racer muscle control. Each racer owns a PolicySlot whose controller can
+// be swapped mid-race; the slot keeps its activations across the swap so the
+// new controller picks up where the old one left off.
+
+use crate::components::cpg::CpgController;
+use crate::components::creature::{Creature, Morphology, Side};
+use crate::components::observation::{ObservationBuilder, PolicyMetadata};
+use crate::components::policy::{AttentionModel, PolicyInput};
+use crate::components::quantized::QuantizedAttentionModel;
+use crate::components::wrappers::PolicyWrappers;
+
+// How long a swapped-in controller takes to fully own the output
+pub const SWAP_BLEND_TIME: f32 = 0.25;
+
+// Activation of a muscle at rest
+const REST: f32 = 1.0;
+
+// What a controller's activations are indexed by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSpace {
+    Limbs,   // one per limb of the morphology's Creature, as the CPG drives them
+    Muscles, // one per muscle of the soft-body mesh, as the attention policies see them
+}
+
+// Which of the morphology's limbs each mesh muscle belongs to, by the
+// Creature's side and pair labels. The meshes do not name their limbs: a
+// muscle's side is the sign of its depth (negative is left), and each side
+// is cut front to back into one band per pair. A flat mesh has no depth and
+// draws a pair's legs one ahead of the other, so its bands are cut per limb,
+// the left leg of each pair taken as the front one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MuscleMap {
+    pub limb_of_muscle: Vec<usize>,
+    pub num_limbs: usize,
+}
+
+impl MuscleMap {
+    pub fn new(morphology: Morphology, builder: &ObservationBuilder) -> Self {
+        let limbs = Creature::new(morphology).limbs;
+        let num_pairs = limbs.iter().map(|limb| limb.pair + 1).max().unwrap_or(0);
+        let limb_index = |side: Side, pair: usize| limbs.iter().position(|limb| limb.side == side && limb.pair == pair).unwrap_or(0);
+        let num_muscles = builder.num_muscles;
+        let mut limb_of_muscle = vec![0; num_muscles];
+
+        let mut front_to_back: Vec<usize> = (0..num_muscles).collect();
+        // muscle_k holds each muscle's rest midpoint, forward coordinate first
+        front_to_back.sort_by(|&a, &b| builder.muscle_k[2 * b].total_cmp(&builder.muscle_k[2 * a]));
+        if builder.muscle_depth.len() == num_muscles {
+            for side in [Side::Left, Side::Right] {
+                let muscles: Vec<usize> = front_to_back.iter().copied().filter(|&m| (builder.muscle_depth[m] < 0.0) == (side == Side::Left)).collect();
+                for (rank, &muscle) in muscles.iter().enumerate() {
+                    limb_of_muscle[muscle] = limb_index(side, rank * num_pairs / muscles.len());
+                }
+            }
+        } else {
+            for (rank, &muscle) in front_to_back.iter().enumerate() {
+                let band = rank * limbs.len() / num_muscles;
+                limb_of_muscle[muscle] = limb_index(if band.is_multiple_of(2) { Side::Left } else { Side::Right }, band / 2);
+            }
+        }
+        Self { limb_of_muscle, num_limbs: limbs.len() }
+    }
+
+    // Every muscle takes its limb's activation
+    pub fn to_muscles(&self, limbs: &[f32]) -> Vec<f32> {
+        self.limb_of_muscle.iter().map(|&limb| limbs.get(limb).copied().unwrap_or(REST)).collect()
+    }
+
+    // Every limb takes the mean of its muscles'
+    pub fn to_limbs(&self, muscles: &[f32]) -> Vec<f32> {
+        let mut sums = vec![(0.0, 0); self.num_limbs];
+        for (&limb, &a) in self.limb_of_muscle.iter().zip(muscles) {
+            sums[limb].0 += a;
+            sums[limb].1 += 1;
+        }
+        sums.iter().map(|&(sum, n)| if n > 0 { sum / n as f32 } else { REST }).collect()
+    }
+
+    fn convert(&self, values: &[f32], from: OutputSpace, to: OutputSpace) -> Vec<f32> {
+        match (from, to) {
+            (OutputSpace::Limbs, OutputSpace::Muscles) => self.to_muscles(values),
+            (OutputSpace::Muscles, OutputSpace::Limbs) => self.to_limbs(values),
+            _ => values.to_vec(),
+        }
+    }
+}
+
+pub enum Controller {
+    Attention(AttentionModel),
+    Quantized(QuantizedAttentionModel),
+    Cpg(CpgController),
+}
+
+impl Controller {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Controller::Attention(_) => "attention",
+            Controller::Quantized(_) => "quantized",
+            Controller::Cpg(_) => "cpg",
+        }
+    }
+
+    pub fn output_space(&self) -> OutputSpace {
+        match self {
+            Controller::Attention(_) | Controller::Quantized(_) => OutputSpace::Muscles,
+            Controller::Cpg(_) => OutputSpace::Limbs,
+        }
+    }
+
+    fn reset(&mut self) {
+        if let Controller::Cpg(cpg) = self {
+            cpg.reset();
+        }
+    }
+
+    // The attention policies output deltas on top of the current activations
+    // and need an observation and the body's policy.json limits; without
+    // them they hold still. The CPG outputs absolute activations on its own
+    // clock.
+    fn act(&mut self, dt: f32, input: Option<&PolicyInput>, meta: Option<&PolicyMetadata>, wrappers: &mut PolicyWrappers, activations: &mut Vec<f32>) {
+        let (da, meta) = match (self, input, meta) {
+            (Controller::Cpg(cpg), _, _) => {
+                cpg.step(dt);
+                *activations = cpg.limb_activations();
+                return;
+            }
+            (Controller::Attention(model), Some(input), Some(meta)) => (model.forward_input(input), meta),
+            (Controller::Quantized(model), Some(input), Some(meta)) => (model.forward_input(input), meta),
+            _ => return,
+        };
+        let da = wrappers.filter(&da);
+        // Nothing carried over for these muscles: start from rest
+        if activations.len() != da.len() {
+            *activations = vec![REST; da.len()];
+        }
+        meta.apply(activations, &da);
+    }
+}
+
+pub struct PolicySlot {
+    pub controller: Option<Controller>,
+    pub meta: Option<PolicyMetadata>, // the body's policy.json, once it has one
+    pub muscles: Option<MuscleMap>,   // the body's muscles, once it has one
+    pub wrappers: PolicyWrappers,
+    activations: Vec<f32>, // the controller's own state, in its output space
+    blend_from: Vec<f32>,  // output at the last swap, in the new controller's space
+    blend: f32,            // 0 right after a swap, 1 once the new controller owns the output
+    output: Vec<f32>,
+}
+
+impl Default for PolicySlot {
+    fn default() -> Self {
+        Self {
+            controller: None,
+            meta: None,
+            muscles: None,
+            wrappers: PolicyWrappers::default(),
+            activations: Vec::new(),
+            blend_from: Vec::new(),
+            blend: 1.0,
+            output: Vec::new(),
+        }
+    }
+}
+
+impl PolicySlot {
+    pub fn name(&self) -> &'static str {
+        self.controller.as_ref().map_or("none", Controller::name)
+    }
+
+    fn space(&self) -> Option<OutputSpace> {
+        self.controller.as_ref().map(Controller::output_space)
+    }
+
+    // `values` from the current controller's space in `to`; empty when they
+    // cannot be carried over, between spaces with no body to map them
+    fn carry(&self, values: &[f32], to: OutputSpace) -> Vec<f32> {
+        match (self.space(), &self.muscles) {
+            (Some(from), _) if from == to => values.to_vec(),
+            (Some(from), Some(muscles)) => muscles.convert(values, from, to),
+            _ => Vec::new(),
+        }
+    }
+
+    // Hot-swap without touching the simulation. Attention policies continue
+    // integrating from the current activations, mapped onto the body's
+    // muscles if the old controller drove limbs; absolute outputs fade in.
+    pub fn swap(&mut self, controller: Controller) {
+        let to = controller.output_space();
+        self.activations = self.carry(&self.activations, to);
+        self.blend_from = self.carry(&self.output, to);
+        self.blend = if self.blend_from.is_empty() { 1.0 } else { 0.0 };
+        self.output = self.blend_from.clone();
+        self.controller = Some(controller);
+    }
+
+    pub fn reset(&mut self) {
+        if let Some(controller) = &mut self.controller {
+            controller.reset();
+        }
+        self.wrappers.reset();
+        self.activations.clear();
+        self.blend_from.clear();
+        self.output.clear();
+        self.blend = 1.0;
+    }
+
+    pub fn step(&mut self, dt: f32, input: Option<&PolicyInput>) {
+        let Some(controller) = &mut self.controller else {
+            return;
+        };
+        let input = input.map(|input| self.wrappers.observe(input));
+        controller.act(dt, input.as_ref(), self.meta.as_ref(), &mut self.wrappers, &mut self.activations);
+
+        self.blend = (self.blend + dt / SWAP_BLEND_TIME).min(1.0);
+        if self.blend_from.len() != self.activations.len() {
+            // A different body since the swap: nothing to fade from
+            self.blend = 1.0;
+        }
+        self.output = if self.blend < 1.0 {
+            self.blend_from.iter().zip(self.activations.iter()).map(|(f, a)| f + (a - f) * self.blend).collect()
+        } else {
+            self.activations.clone()
+        };
+    }
+
+    // In the current controller's output space
+    pub fn output(&self) -> &[f32] {
+        &self.output
+    }
+
+    // One activation per body muscle; rest for all of them without output or
+    // a map from the controller's limbs
+    pub fn muscle_output(&self, num_muscles: usize) -> Vec<f32> {
+        let output = self.carry(&self.output, OutputSpace::Muscles);
+        if output.len() == num_muscles {
+            output
+        } else {
+            vec![REST; num_muscles]
+        }
+    }
+
+    // Sets the creature's limbs, if the output can be read per limb
+    pub fn drive(&self, creature: &mut Creature) {
+        let activations = self.carry(&self.output, OutputSpace::Limbs);
+        if activations.len() != creature.limbs.len() {
+            return;
+        }
+        for (limb, &activation) in creature.limbs.iter_mut().zip(activations.iter()) {
+            for muscle in &mut limb.muscles {
+                muscle.activation = activation;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::observation::MeshData;
+
+    const ARGS_JSON: &str = include_str!("../../data/policies/attn/args.json");
+    const WEIGHTS_JSON: &str = include_str!("../../data/policies/attn/weights.json");
+    const MESH_JSON: &str = include_str!("../../data/agents/biped/mesh.json");
+    const POLICY_JSON: &str = include_str!("../../data/agents/biped/policy.json");
+    const QUADRUPED_MESH_JSON: &str = include_str!("../../data/agents/quadruped/mesh.json");
+    const QUADRUPED_POLICY_JSON: &str = include_str!("../../data/agents/quadruped/policy.json");
+
+    fn cpg_slot(phase: f32) -> PolicySlot {
+        let mut cpg = CpgController::for_morphology(Morphology::Biped);
+        cpg.phase = phase;
+        let mut slot = PolicySlot::default();
+        slot.swap(Controller::Cpg(cpg));
+        slot
+    }
+
+    #[test]
+    fn test_swap_blends_smoothly() {
+        let mut slot = cpg_slot(0.0);
+        for _ in 0..10 {
+            slot.step(0.02, None);
+        }
+        let before = slot.output().to_vec();
+
+        // Opposite phase: without blending the output would jump
+        let mut cpg = CpgController::for_morphology(Morphology::Biped);
+        cpg.phase = std::f32::consts::PI;
+        slot.swap(Controller::Cpg(cpg));
+        slot.step(0.01, None);
+
+        let max_jump = 0.01 / SWAP_BLEND_TIME + 0.05; // blend share + CPG drift over one step
+        for (b, a) in before.iter().zip(slot.output()) {
+            assert!((a - b).abs() < max_jump, "{} -> {}", b, a);
+        }
+
+        for _ in 0..20 {
+            slot.step(0.02, None);
+        }
+        assert_eq!(slot.name(), "cpg");
+        assert_eq!(slot.blend, 1.0);
+    }
+
+    #[test]
+    fn test_muscle_map() {
+        let builder = ObservationBuilder::from_json(MESH_JSON, POLICY_JSON).unwrap();
+        let map = MuscleMap::new(Morphology::Biped, &builder);
+        assert_eq!(map.limb_of_muscle.len(), 19);
+        assert_eq!(map.limb_of_muscle.iter().filter(|&&limb| limb == 0).count(), 10);
+        // The front limb's muscles sit ahead of the back limb's
+        let forward = |limb| map.limb_of_muscle.iter().enumerate().filter(move |&(_, &l)| l == limb).map(|(i, _)| builder.muscle_k[2 * i]);
+        assert!(forward(1).fold(f32::MIN, f32::max) <= forward(0).fold(f32::MAX, f32::min));
+
+        let muscles = map.to_muscles(&[0.25, 0.75]);
+        assert!(muscles.iter().all(|&a| a == 0.25 || a == 0.75));
+        assert_eq!(map.to_limbs(&muscles), vec![0.25, 0.75]);
+
+        let builder = ObservationBuilder::from_json(QUADRUPED_MESH_JSON, QUADRUPED_POLICY_JSON).unwrap();
+        let map = MuscleMap::new(Morphology::Quadruped, &builder);
+        assert_eq!(map.num_limbs, 4);
+        assert!((0..4).all(|limb| map.limb_of_muscle.contains(&limb)));
+    }
+
+    #[test]
+    fn test_muscle_map_keeps_sides_apart() {
+        let builder = ObservationBuilder::from_json(QUADRUPED_MESH_JSON, QUADRUPED_POLICY_JSON).unwrap();
+        let map = MuscleMap::new(Morphology::Quadruped, &builder);
+        let limbs = Creature::new(Morphology::Quadruped).limbs;
+
+        // Only the left legs contract
+        let pattern: Vec<f32> = limbs.iter().map(|limb| if limb.side == Side::Left { 0.25 } else { 1.0 }).collect();
+        let muscles = map.to_muscles(&pattern);
+        for (m, &a) in muscles.iter().enumerate() {
+            assert_eq!(a == 0.25, builder.muscle_depth[m] < 0.0, "muscle {} at depth {}", m, builder.muscle_depth[m]);
+        }
+        assert_eq!(map.to_limbs(&muscles), pattern);
+
+        // Every limb gets muscles from its own side only, front pair ahead of the hind one
+        let forward = |limb| map.limb_of_muscle.iter().enumerate().filter(move |&(_, &l)| l == limb).map(|(i, _)| builder.muscle_k[2 * i]);
+        for (i, limb) in limbs.iter().enumerate().filter(|(_, limb)| limb.pair == 0) {
+            let hind = limbs.iter().position(|l| l.side == limb.side && l.pair == 1).unwrap();
+            assert!(forward(hind).fold(f32::MIN, f32::max) <= forward(i).fold(f32::MAX, f32::min));
+        }
+        let distinct = [0.125, 0.25, 0.5, 1.0];
+        assert_eq!(map.to_limbs(&map.to_muscles(&distinct)), distinct);
+    }
+
+    #[test]
+    fn test_policy_continues_from_activations() {
+        let meta = PolicyMetadata::from_json(POLICY_JSON).unwrap();
+        let mesh = MeshData::from_json(MESH_JSON).unwrap();
+        let builder = ObservationBuilder::new(&mesh, meta.center_vertex_id, meta.forward_vertex_id);
+        let input = builder.build(&mesh.pos, &vec![[0.0, 0.0]; mesh.pos.len()]);
+        let map = MuscleMap::new(Morphology::Biped, &builder);
+
+        let mut slot = cpg_slot(0.0);
+        slot.meta = Some(meta);
+        slot.muscles = Some(map.clone());
+        slot.step(0.1, None);
+        assert_eq!(slot.output().len(), 2);
+        let before = map.to_muscles(slot.output());
+        assert_eq!(slot.muscle_output(19), before);
+
+        let model = AttentionModel::try_new(ARGS_JSON, WEIGHTS_JSON).unwrap();
+        let da = model.forward_input(&input);
+        slot.swap(Controller::Attention(model));
+
+        // No observation: the policy holds the limbs' activations, now per muscle
+        slot.step(0.0, None);
+        assert_eq!(slot.output(), &before[..]);
+
+        slot.step(SWAP_BLEND_TIME, Some(&input));
+        let mut expected = before;
+        meta.apply(&mut expected, &da);
+        assert_eq!(slot.output(), &expected[..]);
+
+        // Back to the CPG, the creature's limbs read the muscles per limb
+        let mut creature = Creature::new(Morphology::Biped);
+        slot.swap(Controller::Cpg(CpgController::for_morphology(Morphology::Biped)));
+        slot.drive(&mut creature);
+        assert_eq!(creature.limbs[0].muscles[0].activation, map.to_limbs(&expected)[0]);
+    }
+
+    #[test]
+    fn test_swap_without_body_starts_over() {
+        // Nothing maps two limbs onto the policy's muscles, so nothing carries
+        let mut slot = cpg_slot(0.0);
+        slot.step(0.1, None);
+        slot.swap(Controller::Attention(AttentionModel::try_new(ARGS_JSON, WEIGHTS_JSON).unwrap()));
+        slot.step(0.0, None);
+        assert!(slot.output().is_empty());
+        assert_eq!(slot.muscle_output(19), vec![1.0; 19]);
+
+        let mut creature = Creature::new(Morphology::Biped);
+        slot.drive(&mut creature);
+        assert_eq!(creature.limbs[0].muscles[0].activation, 0.0);
+    }
+}
@@ -0,0 +1,151 @@
+use wasm_bindgen::prelude::*;
+use std::f32::consts::PI;
+use crate::components::creature::{Creature, Morphology};
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gait {
+    Alternating = 0, // Biped
+    Trot = 1,        // Quadruped, diagonal pairs together
+    Pace = 2,        // Quadruped, lateral pairs together
+    Gallop = 3,      // Quadruped, front pair then hind pair
+    Tripod = 4,      // Hexapod, two alternating tripods
+}
+
+impl Gait {
+    pub fn default_for(morphology: Morphology) -> Gait {
+        match morphology {
+            Morphology::Biped => Gait::Alternating,
+            Morphology::Quadruped => Gait::Trot,
+            Morphology::Hexapod => Gait::Tripod,
+        }
+    }
+
+    pub fn supports(self, morphology: Morphology) -> bool {
+        self.limb_phases().len() == Creature::new(morphology).limbs.len()
+    }
+
+    // Phase of each limb as a fraction of the cycle, in the limb order
+    // produced by Creature::new (left/right pairs, front to back).
+    pub fn limb_phases(self) -> &'static [f32] {
+        match self {
+            Gait::Alternating => &[0.0, 0.5],
+            Gait::Trot => &[0.0, 0.5, 0.5, 0.0],
+            Gait::Pace => &[0.0, 0.5, 0.0, 0.5],
+            Gait::Gallop => &[0.0, 0.1, 0.5, 0.6],
+            Gait::Tripod => &[0.0, 0.5, 0.5, 0.0, 0.0, 0.5],
+        }
+    }
+}
+
+// Open-loop central pattern generator: one sine oscillator per limb, locked
+// to the gait's phase offsets. Needs no trained weights.
+#[derive(Debug, Clone)]
+pub struct CpgController {
+    pub gait: Gait,
+    pub frequency: f32, // Hz
+    pub amplitude: f32,
+    pub phase: f32,     // radians, shifts every oscillator
+    pub baseline: f32,
+    time: f32,
+}
+
+impl CpgController {
+    pub fn new(morphology: Morphology, gait: Gait) -> Result<Self, String> {
+        if !gait.supports(morphology) {
+            return Err(format!("gait {:?} does not fit a {:?}", gait, morphology));
+        }
+
+        let frequency = match morphology {
+            Morphology::Biped => 1.5,
+            Morphology::Quadruped => 2.0,
+            Morphology::Hexapod => 2.5,
+        };
+
+        Ok(Self {
+            gait,
+            frequency,
+            // Oscillate over [0.25, 1.0], the same range the attention policy
+            // is clamped to (min_a in policy.json)
+            amplitude: 0.375,
+            phase: 0.0,
+            baseline: 0.625,
+            time: 0.0,
+        })
+    }
+
+    pub fn for_morphology(morphology: Morphology) -> Self {
+        Self::new(morphology, Gait::default_for(morphology)).unwrap()
+    }
+
+    pub fn set_params(&mut self, frequency: f32, amplitude: f32, phase: f32) {
+        self.frequency = frequency.max(0.0);
+        self.amplitude = amplitude.max(0.0);
+        self.phase = phase;
+    }
+
+    pub fn reset(&mut self) {
+        self.time = 0.0;
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    pub fn limb_activations(&self) -> Vec<f32> {
+        self.gait
+            .limb_phases()
+            .iter()
+            .map(|&offset| {
+                let angle = 2.0 * PI * (self.frequency * self.time + offset) + self.phase;
+                (self.baseline + self.amplitude * angle.sin()).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+
+    pub fn drive(&self, creature: &mut Creature) {
+        let activations = self.limb_activations();
+        for (limb, &activation) in creature.limbs.iter_mut().zip(activations.iter()) {
+            for muscle in &mut limb.muscles {
+                muscle.activation = activation;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gait_fits_morphology() {
+        assert!(CpgController::new(Morphology::Biped, Gait::Alternating).is_ok());
+        assert!(CpgController::new(Morphology::Quadruped, Gait::Gallop).is_ok());
+        assert!(CpgController::new(Morphology::Hexapod, Gait::Tripod).is_ok());
+        assert!(CpgController::new(Morphology::Biped, Gait::Trot).is_err());
+    }
+
+    #[test]
+    fn test_biped_alternates() {
+        let mut cpg = CpgController::for_morphology(Morphology::Biped);
+        cpg.step(0.1);
+        let a = cpg.limb_activations();
+        // Half a cycle apart: mirror images around the baseline
+        assert!((a[0] - cpg.baseline + (a[1] - cpg.baseline)).abs() < 1e-5);
+        assert!(a.iter().all(|&x| (0.25..=1.0).contains(&x)));
+    }
+
+    #[test]
+    fn test_tripod_drives_creature() {
+        let mut creature = Creature::new(Morphology::Hexapod);
+        let mut cpg = CpgController::for_morphology(Morphology::Hexapod);
+        cpg.step(0.05);
+        cpg.drive(&mut creature);
+
+        let a: Vec<f32> = creature.limbs.iter().map(|l| l.muscles[0].activation).collect();
+        assert_eq!(a[0], a[3]);
+        assert_eq!(a[0], a[4]);
+        assert_eq!(a[1], a[2]);
+        assert_ne!(a[0], a[1]);
+    }
+}
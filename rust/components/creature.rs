@@ -15,10 +15,19 @@ pub struct Muscle {
     pub activation: f32,
 }
 
+// Limbs come in left/right pairs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct Limb {
     pub length: f32,
+    pub side: Side,
+    pub pair: usize, // 0 is the front pair
     pub muscles: Vec<Muscle>,
 }
 
@@ -30,6 +39,17 @@ pub struct Creature {
     pub limbs: Vec<Limb>,
 }
 
+impl Morphology {
+    pub fn from_u8(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Morphology::Biped),
+            1 => Some(Morphology::Quadruped),
+            2 => Some(Morphology::Hexapod),
+            _ => None,
+        }
+    }
+}
+
 impl Creature {
     pub fn new(morphology: Morphology) -> Self {
         let (limbs, mass) = match morphology {
@@ -45,30 +65,36 @@ impl Creature {
         }
     }
 
+    // Every muscle at full activation, in N
+    pub fn max_force(&self) -> f32 {
+        self.limbs.iter().flat_map(|limb| limb.muscles.iter()).map(|m| m.max_force).sum()
+    }
+
+    // Limbs in CPG order: left then right of each pair, front to back
     fn create_biped_limbs() -> Vec<Limb> {
         vec![
-            Limb { length: 1.0, muscles: vec![Muscle { max_force: 800.0, activation: 0.0 }] },
-            Limb { length: 1.0, muscles: vec![Muscle { max_force: 800.0, activation: 0.0 }] },
+            Limb { length: 1.0, side: Side::Left, pair: 0, muscles: vec![Muscle { max_force: 800.0, activation: 0.0 }] },
+            Limb { length: 1.0, side: Side::Right, pair: 0, muscles: vec![Muscle { max_force: 800.0, activation: 0.0 }] },
         ]
     }
 
     fn create_quadruped_limbs() -> Vec<Limb> {
         vec![
-            Limb { length: 0.8, muscles: vec![Muscle { max_force: 600.0, activation: 0.0 }] },
-            Limb { length: 0.8, muscles: vec![Muscle { max_force: 600.0, activation: 0.0 }] },
-            Limb { length: 0.8, muscles: vec![Muscle { max_force: 600.0, activation: 0.0 }] },
-            Limb { length: 0.8, muscles: vec![Muscle { max_force: 600.0, activation: 0.0 }] },
+            Limb { length: 0.8, side: Side::Left, pair: 0, muscles: vec![Muscle { max_force: 600.0, activation: 0.0 }] },
+            Limb { length: 0.8, side: Side::Right, pair: 0, muscles: vec![Muscle { max_force: 600.0, activation: 0.0 }] },
+            Limb { length: 0.8, side: Side::Left, pair: 1, muscles: vec![Muscle { max_force: 600.0, activation: 0.0 }] },
+            Limb { length: 0.8, side: Side::Right, pair: 1, muscles: vec![Muscle { max_force: 600.0, activation: 0.0 }] },
         ]
     }
 
     fn create_hexapod_limbs() -> Vec<Limb> {
         vec![
-            Limb { length: 0.7, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
-            Limb { length: 0.7, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
-            Limb { length: 0.7, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
-            Limb { length: 0.7, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
-            Limb { length: 0.7, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
-            Limb { length: 0.7, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
+            Limb { length: 0.7, side: Side::Left, pair: 0, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
+            Limb { length: 0.7, side: Side::Right, pair: 0, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
+            Limb { length: 0.7, side: Side::Left, pair: 1, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
+            Limb { length: 0.7, side: Side::Right, pair: 1, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
+            Limb { length: 0.7, side: Side::Left, pair: 2, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
+            Limb { length: 0.7, side: Side::Right, pair: 2, muscles: vec![Muscle { max_force: 500.0, activation: 0.0 }] },
         ]
     }
 }
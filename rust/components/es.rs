@@ -0,0 +1,171 @@
+// OpenAI-style evolution strategies over the flat AttentionModel weights:
+// antithetic Gaussian perturbations, centered-rank fitness shaping and a plain
+// gradient step. Scores are center-vertex x displacement over a headless
+// rollout, the number generate_trajectory_with_attn_policy.py reports.
+
+use crate::components::locomotion::{Locomotion, SIM_DT};
+use crate::components::policy::AttentionModel;
+use crate::components::rng::Rng;
+
+#[derive(Debug, Clone)]
+pub struct EsConfig {
+    pub population: usize, // antithetic pairs per generation
+    pub sigma: f32,
+    pub learning_rate: f32,
+    pub rollout_steps: usize,
+    pub seed: u64,
+}
+
+impl Default for EsConfig {
+    fn default() -> Self {
+        Self {
+            population: 16,
+            sigma: 0.02,
+            learning_rate: 0.01,
+            rollout_steps: 100,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Generation {
+    pub index: usize,
+    pub mean_score: f32,
+    pub best_score: f32,
+    pub score: f32, // of the updated weights
+}
+
+pub fn rollout_score(model: &AttentionModel, mesh_json: &str, policy_json: &str, steps: usize) -> Result<f32, String> {
+    let mut loco = Locomotion::new(mesh_json, policy_json)?;
+    let x0 = loco.center()[0];
+    for _ in 0..steps {
+        let da = model.forward_input(&loco.observe());
+        loco.step(&da, SIM_DT);
+    }
+    let dx = loco.center()[0] - x0;
+    // An exploded sim must never look like progress
+    Ok(if dx.is_finite() { dx } else { f32::NEG_INFINITY })
+}
+
+// Ranks mapped to [-0.5, 0.5], so the update ignores score scale and outliers
+fn centered_ranks(scores: &[f32]) -> Vec<f32> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+    let mut ranks = vec![0.0; scores.len()];
+    let denom = (scores.len().max(2) - 1) as f32;
+    for (rank, &i) in order.iter().enumerate() {
+        ranks[i] = rank as f32 / denom - 0.5;
+    }
+    ranks
+}
+
+pub struct EsTrainer {
+    pub config: EsConfig,
+    model: AttentionModel,
+    params: Vec<f32>,
+    mesh_json: String,
+    policy_json: String,
+    rng: Rng,
+    generation: usize,
+}
+
+impl EsTrainer {
+    pub fn new(model: AttentionModel, mesh_json: &str, policy_json: &str, config: EsConfig) -> Result<Self, String> {
+        // Fail early on a bad mesh rather than in the middle of a generation
+        Locomotion::new(mesh_json, policy_json)?;
+        Ok(Self {
+            params: model.parameters(),
+            rng: Rng::new(config.seed),
+            config,
+            model,
+            mesh_json: mesh_json.to_string(),
+            policy_json: policy_json.to_string(),
+            generation: 0,
+        })
+    }
+
+    fn score(&mut self, params: &[f32]) -> Result<f32, String> {
+        self.model.set_parameters(params);
+        rollout_score(&self.model, &self.mesh_json, &self.policy_json, self.config.rollout_steps)
+    }
+
+    pub fn step(&mut self) -> Result<Generation, String> {
+        let dim = self.params.len();
+        let n = self.config.population.max(1);
+        let sigma = self.config.sigma;
+
+        let noise: Vec<Vec<f32>> = (0..n).map(|_| (0..dim).map(|_| self.rng.gaussian()).collect()).collect();
+
+        let mut scores = Vec::with_capacity(2 * n);
+        for eps in &noise {
+            for sign in [1.0, -1.0] {
+                let candidate: Vec<f32> = self.params.iter().zip(eps).map(|(p, e)| p + sign * sigma * e).collect();
+                scores.push(self.score(&candidate)?);
+            }
+        }
+
+        let ranks = centered_ranks(&scores);
+        let step = self.config.learning_rate / (2.0 * n as f32 * sigma);
+        for (i, eps) in noise.iter().enumerate() {
+            let weight = (ranks[2 * i] - ranks[2 * i + 1]) * step;
+            for (p, e) in self.params.iter_mut().zip(eps) {
+                *p += weight * e;
+            }
+        }
+
+        let params = self.params.clone();
+        let score = self.score(&params)?;
+        let finite: Vec<f32> = scores.iter().copied().filter(|s| s.is_finite()).collect();
+
+        let generation = Generation {
+            index: self.generation,
+            mean_score: finite.iter().sum::<f32>() / finite.len().max(1) as f32,
+            best_score: scores.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+            score,
+        };
+        self.generation += 1;
+        Ok(generation)
+    }
+
+    pub fn model(&self) -> &AttentionModel {
+        &self.model
+    }
+
+    pub fn into_model(self) -> AttentionModel {
+        self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARGS_JSON: &str = include_str!("../../data/policies/attn/args.json");
+    const WEIGHTS_JSON: &str = include_str!("../../data/policies/attn/weights.json");
+    const MESH_JSON: &str = include_str!("../../data/agents/biped/mesh.json");
+    const POLICY_JSON: &str = include_str!("../../data/agents/biped/policy.json");
+
+    fn trainer(seed: u64) -> EsTrainer {
+        let model = AttentionModel::try_new(ARGS_JSON, WEIGHTS_JSON).unwrap();
+        let config = EsConfig { population: 2, rollout_steps: 5, seed, ..EsConfig::default() };
+        EsTrainer::new(model, MESH_JSON, POLICY_JSON, config).unwrap()
+    }
+
+    #[test]
+    fn test_centered_ranks() {
+        assert_eq!(centered_ranks(&[3.0, -1.0, 10.0]), vec![0.0, -0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_deterministic_from_seed() {
+        let (mut a, mut b, mut c) = (trainer(7), trainer(7), trainer(8));
+        let generation = a.step().unwrap();
+        assert!(generation.score.is_finite() && generation.best_score.is_finite());
+        assert_eq!(Ok(generation), b.step());
+        c.step().unwrap();
+
+        assert_eq!(a.model().parameters(), b.model().parameters());
+        assert_ne!(a.model().parameters(), c.model().parameters());
+    }
+}
@@ -0,0 +1,196 @@
+// Per-racer controls. Whatever the device, a racer reads one InputState a
+// tick; bindings turn keyboard and gamepad state into it.
+
+use serde::Deserialize;
+use crate::components::racer::MAX_RACERS;
+
+// Floats per racer in update_inputs: throttle, brake, steer, morph
+pub const INPUT_STRIDE: usize = 4;
+
+// Floats per pad in update_devices: the Gamepad API's standard mapping has
+// 17 buttons and 4 axes
+pub const GAMEPAD_BUTTONS: usize = 17;
+pub const GAMEPAD_AXES: usize = 4;
+pub const GAMEPAD_STRIDE: usize = GAMEPAD_BUTTONS + GAMEPAD_AXES;
+
+// Sticks read as centered inside this
+const AXIS_DEAD_ZONE: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct InputState {
+    pub throttle: f32, // 0 to 1
+    pub brake: f32,    // 0 to 1
+    pub steer: f32,    // -1 left to 1 right
+    pub morph: bool,
+}
+
+impl InputState {
+    // One racer's INPUT_STRIDE values; morph is on above 0.5
+    pub fn from_slice(values: &[f32]) -> Self {
+        let value = |i: usize| values.get(i).copied().filter(|v| v.is_finite()).unwrap_or(0.0);
+        Self {
+            throttle: value(0).clamp(0.0, 1.0),
+            brake: value(1).clamp(0.0, 1.0),
+            steer: value(2).clamp(-1.0, 1.0),
+            morph: value(3) > 0.5,
+        }
+    }
+
+    // Keys count fully; with a gamepad as well, the stronger of the two wins
+    pub fn read(binding: &InputBinding, pressed: impl Fn(&str) -> bool, gamepads: &[f32]) -> Self {
+        let key = |code: &str| if !code.is_empty() && pressed(code) { 1.0 } else { 0.0 };
+        let mut input = Self {
+            throttle: key(&binding.throttle),
+            brake: key(&binding.brake),
+            steer: key(&binding.steer_right) - key(&binding.steer_left),
+            morph: key(&binding.morph) > 0.0,
+        };
+
+        let pad = binding.gamepad.and_then(|g| Some((g, gamepads.get(g.index * GAMEPAD_STRIDE..(g.index + 1) * GAMEPAD_STRIDE)?)));
+        if let Some((g, pad)) = pad {
+            let value = |i: usize| Some(pad[i]).filter(|v| v.is_finite()).unwrap_or(0.0);
+            let button = |i: usize| if i < GAMEPAD_BUTTONS { value(i).clamp(0.0, 1.0) } else { 0.0 };
+            let axis = |i: usize| if i < GAMEPAD_AXES { value(GAMEPAD_BUTTONS + i).clamp(-1.0, 1.0) } else { 0.0 };
+            let stick = axis(g.steer);
+            input.throttle = input.throttle.max(button(g.throttle));
+            input.brake = input.brake.max(button(g.brake));
+            if stick.abs() > AXIS_DEAD_ZONE && stick.abs() > input.steer.abs() {
+                input.steer = stick;
+            }
+            input.morph |= button(g.morph) > 0.5;
+        }
+        input.steer = input.steer.clamp(-1.0, 1.0);
+        input
+    }
+}
+
+// Standard-mapping button and axis indices
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GamepadBinding {
+    pub index: usize, // navigator.getGamepads() slot
+    #[serde(default = "default_pad_throttle")]
+    pub throttle: usize,
+    #[serde(default = "default_pad_brake")]
+    pub brake: usize,
+    #[serde(default)]
+    pub steer: usize, // axis
+    #[serde(default)]
+    pub morph: usize,
+}
+
+fn default_pad_throttle() -> usize {
+    7 // right trigger
+}
+
+fn default_pad_brake() -> usize {
+    6 // left trigger
+}
+
+impl GamepadBinding {
+    pub fn for_pad(index: usize) -> Self {
+        Self { index, throttle: default_pad_throttle(), brake: default_pad_brake(), steer: 0, morph: 0 }
+    }
+}
+
+// KeyboardEvent.code values; an empty code is unbound
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct InputBinding {
+    pub throttle: String,
+    pub brake: String,
+    #[serde(default)]
+    pub steer_left: String,
+    #[serde(default)]
+    pub steer_right: String,
+    #[serde(default)]
+    pub morph: String, // cycles biped -> quadruped -> hexapod
+    #[serde(default)]
+    pub gamepad: Option<GamepadBinding>,
+}
+
+// (throttle, brake, steer left, steer right, morph). The first two are the
+// original WASD / arrow-key players; the rest share the keyboard without
+// steering, or use their gamepad.
+const DEFAULT_BINDINGS: [(&str, &str, &str, &str, &str); MAX_RACERS] = [
+    ("KeyW", "KeyS", "KeyA", "KeyD", "KeyE"),
+    ("ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight", "ShiftRight"),
+    ("KeyI", "KeyK", "", "", "KeyU"),
+    ("Numpad8", "Numpad5", "", "", "Numpad9"),
+    ("KeyT", "KeyG", "", "", "KeyY"),
+    ("KeyO", "KeyL", "", "", "Period"),
+    ("Digit1", "KeyQ", "", "", "Digit2"),
+    ("Digit0", "KeyP", "", "", "Minus"),
+];
+
+impl InputBinding {
+    pub fn default_for(index: usize) -> Self {
+        let (throttle, brake, steer_left, steer_right, morph) = DEFAULT_BINDINGS[index % MAX_RACERS];
+        Self {
+            throttle: throttle.to_string(),
+            brake: brake.to_string(),
+            steer_left: steer_left.to_string(),
+            steer_right: steer_right.to_string(),
+            morph: morph.to_string(),
+            gamepad: Some(GamepadBinding::for_pad(index)),
+        }
+    }
+
+    // A JSON array of bindings, one per racer in lane order
+    pub fn table_from_json(json: &str) -> Result<Vec<Self>, String> {
+        serde_json::from_str(json).map_err(|e| format!("bindings.json: {}", e))
+    }
+
+    pub fn keys(&self) -> [&str; 5] {
+        [&self.throttle, &self.brake, &self.steer_left, &self.steer_right, &self.morph]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_and_gamepad() {
+        let binding = InputBinding::default_for(1);
+        let input = InputState::read(&binding, |code| code == "ArrowUp" || code == "ArrowLeft", &[]);
+        assert_eq!(input, InputState { throttle: 1.0, brake: 0.0, steer: -1.0, morph: false });
+
+        // Pad 1: half right trigger, stick right, A pressed
+        let mut pads = vec![0.0; 2 * GAMEPAD_STRIDE];
+        pads[GAMEPAD_STRIDE + 7] = 0.5;
+        pads[GAMEPAD_STRIDE] = 1.0;
+        pads[GAMEPAD_STRIDE + GAMEPAD_BUTTONS] = 0.6;
+        let input = InputState::read(&binding, |_| false, &pads);
+        assert_eq!(input, InputState { throttle: 0.5, brake: 0.0, steer: 0.6, morph: true });
+
+        // Inside the dead zone the stick does not steer
+        pads[GAMEPAD_STRIDE + GAMEPAD_BUTTONS] = 0.1;
+        assert_eq!(InputState::read(&binding, |_| false, &pads).steer, 0.0);
+        // Racer 0 reads pad 0, which is idle
+        assert_eq!(InputState::read(&InputBinding::default_for(0), |_| false, &pads), InputState::default());
+
+        // A misbehaving pad's non-finite values read as released and centered
+        pads[GAMEPAD_STRIDE..].fill(f32::NAN);
+        pads[GAMEPAD_STRIDE + 7] = f32::INFINITY;
+        assert_eq!(InputState::read(&binding, |_| false, &pads), InputState::default());
+        assert_eq!(InputState::read(&binding, |code| code == "ArrowUp", &pads).throttle, 1.0);
+    }
+
+    #[test]
+    fn test_from_slice_clamps() {
+        let input = InputState::from_slice(&[2.0, f32::NAN, -3.0, 1.0]);
+        assert_eq!(input, InputState { throttle: 1.0, brake: 0.0, steer: -1.0, morph: true });
+        assert_eq!(InputState::from_slice(&[]), InputState::default());
+    }
+
+    #[test]
+    fn test_table_from_json() {
+        let table = InputBinding::table_from_json(
+            r#"[{"throttle": "KeyW", "brake": "KeyS"}, {"throttle": "KeyX", "brake": "KeyZ", "gamepad": {"index": 2, "steer": 2}}]"#,
+        )
+        .unwrap();
+        assert_eq!(table[0].morph, "");
+        assert_eq!(table[0].gamepad, None);
+        assert_eq!(table[1].gamepad, Some(GamepadBinding { index: 2, throttle: 7, brake: 6, steer: 2, morph: 0 }));
+        assert!(InputBinding::table_from_json(r#"[{"brake": "KeyS"}]"#).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::components::observation::{ObservationBuilder, PolicyMetadata};
+use crate::components::policy::PolicyInput;
+use crate::components::soft_body::SoftBodySimulation;
+
+// Same step size as algovivo's default System.h
+pub const SIM_DT: f32 = 0.033;
+
+// One soft-body creature driven by muscle activations, following the loop in
+// generate_trajectory_with_attn_policy.py: observe -> da -> a -> sim step.
+pub struct Locomotion {
+    pub sim: SoftBodySimulation,
+    pub builder: ObservationBuilder,
+    pub meta: PolicyMetadata,
+    pub activations: Vec<f32>,
+}
+
+impl Locomotion {
+    pub fn new(mesh_json: &str, policy_json: &str) -> Result<Self, String> {
+        let builder = ObservationBuilder::from_json(mesh_json, policy_json)?;
+        let meta = PolicyMetadata::from_json(policy_json)?;
+        let sim = SoftBodySimulation::new(mesh_json);
+        Ok(Self {
+            activations: vec![1.0; sim.num_muscles()],
+            sim,
+            builder,
+            meta,
+        })
+    }
+
+    pub fn observe(&self) -> PolicyInput {
+        self.builder.build(&self.sim.get_node_positions(), &self.sim.get_node_velocities())
+    }
+
+    pub fn step(&mut self, da: &[f32], dt: f32) {
+        self.meta.apply(&mut self.activations, da);
+        self.sim.step(dt, &self.activations);
+    }
+
+    // Absolute activations, as a PolicySlot outputs them, instead of da
+    pub fn drive(&mut self, activations: &[f32], dt: f32) {
+        self.activations.copy_from_slice(activations);
+        self.sim.step(dt, &self.activations);
+    }
+
+    pub fn center(&self) -> [f32; 2] {
+        self.sim.get_node_positions()[self.meta.center_vertex_id]
+    }
+
+    // Every node has the same mass
+    pub fn center_of_mass(&self) -> [f32; 2] {
+        let pos = self.sim.get_node_positions();
+        let n = pos.len().max(1) as f32;
+        let sum = pos.iter().fold([0.0, 0.0], |acc, p| [acc[0] + p[0], acc[1] + p[1]]);
+        [sum[0] / n, sum[1] / n]
+    }
+}
@@ -1,6 +1,28 @@
 pub mod state;
+pub mod clock;
+pub mod phase;
+pub mod racer;
+pub mod input;
+pub mod bot;
+pub mod mode;
+pub mod rope;
 // pub mod physics;
-// pub mod rules;
+pub mod rules;
+pub mod track;
+pub mod terrain;
+pub mod stamina;
 pub mod creature;
 pub mod policy;
+pub mod cpg;
+pub mod controller;
+pub mod wrappers;
+pub mod tensor;
+pub mod weights_bin;
+pub mod quantized;
+pub mod observation;
+pub mod trajectory;
+pub mod rng;
+pub mod locomotion;
+pub mod es;
+pub mod bc;
 pub mod soft_body;
@@ -0,0 +1,218 @@
+use wasm_bindgen::prelude::*;
+use crate::components::racer::{Racer, MAX_RACERS};
+use crate::components::rope::Rope;
+use crate::components::rules::LOOP_RADIUS;
+use crate::components::track::Track;
+use std::cmp::Ordering;
+
+// Tug-of-war: creatures start this far either side of the rope's center,
+// and whoever drags the rope marker this far to their side wins
+pub const TUG_START_X: f32 = 5.0;
+pub const TUG_WIN_OFFSET: f32 = 3.0;
+
+// Lap races let racers back up, at this fraction of their top speed, so
+// they can turn the wrong way
+pub const LAPS_REVERSE_FRACTION: f32 = 0.25;
+
+// Finish times closer than this are a dead heat; crossing times are
+// interpolated, so only racers that really cross together get one
+pub const DEAD_HEAT_TIME: f64 = 1e-6;
+
+// Once the first racer is over the line the rest get this long, in seconds,
+// to follow before they are out of the race
+pub const FINISH_TIMEOUT: f64 = 15.0;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameMode {
+    Race = 0,        // side by side, first over the finish line
+    TugOfWar = 1,    // two creatures facing each other, pulling apart
+    TimeTrial = 2,   // everyone runs the course, fastest time wins
+    Laps = 3,        // round a closed loop, first to finish every lap
+    PhysicsRace = 4, // like Race, but soft bodies run it under their policies
+}
+
+impl GameMode {
+    pub fn from_u8(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(GameMode::Race),
+            1 => Some(GameMode::TugOfWar),
+            2 => Some(GameMode::TimeTrial),
+            3 => Some(GameMode::Laps),
+            4 => Some(GameMode::PhysicsRace),
+            _ => None,
+        }
+    }
+
+    pub fn default_num_racers(self) -> usize {
+        match self {
+            GameMode::Race | GameMode::TugOfWar | GameMode::Laps | GameMode::PhysicsRace => 2,
+            GameMode::TimeTrial => 1,
+        }
+    }
+
+    pub fn supports(self, num_racers: usize) -> bool {
+        match self {
+            GameMode::TugOfWar => num_racers == 2,
+            GameMode::Race | GameMode::TimeTrial | GameMode::Laps | GameMode::PhysicsRace => (1..=MAX_RACERS).contains(&num_racers),
+        }
+    }
+
+    // Tug-of-war has no track; the others need one that ends, or one that
+    // loops for laps
+    pub fn default_track(self) -> Track {
+        match self {
+            GameMode::Laps => Track::circle(LOOP_RADIUS),
+            _ => Track::straight(),
+        }
+    }
+
+    pub fn accepts(self, track: &Track) -> bool {
+        match self {
+            GameMode::Race | GameMode::TimeTrial | GameMode::PhysicsRace => !track.closed,
+            GameMode::Laps => track.closed,
+            GameMode::TugOfWar => false,
+        }
+    }
+
+    // Start positions; racers come in from Racer::new in their lanes
+    pub fn layout(self, racers: &mut [Racer], track: &Track) {
+        let num_lanes = racers.len();
+        match self {
+            GameMode::Race | GameMode::TimeTrial | GameMode::Laps | GameMode::PhysicsRace => {
+                let start = if track.closed { track.finish } else { 0.0 };
+                for racer in racers {
+                    racer.lane_width = track.lane_width;
+                    racer.lateral = 0.0;
+                    (racer.x, racer.z, racer.rotation_y) = track.position(start, racer.lane_offset(num_lanes));
+                    racer.stats.distance = start;
+                    if self == GameMode::Laps {
+                        racer.reverse = LAPS_REVERSE_FRACTION;
+                    }
+                }
+            }
+            GameMode::TugOfWar => {
+                for (racer, (x, rotation_y)) in racers.iter_mut().zip([(-TUG_START_X, 0.0), (TUG_START_X, std::f32::consts::PI)]) {
+                    racer.x = x;
+                    racer.z = 0.0;
+                    racer.rotation_y = rotation_y;
+                }
+            }
+        }
+    }
+
+    // Update rules for one tick, after every racer has read its input.
+    // Racers follow their lane along the track.
+    pub fn step(self, racers: &mut [Racer], rope: Option<&mut Rope>, track: &Track, delta: f32) {
+        let num_lanes = racers.len();
+        match self {
+            GameMode::Race => {
+                for racer in racers.iter_mut() {
+                    racer.advance(delta);
+                    (racer.x, racer.z, racer.rotation_y) = track.position(racer.stats.distance, racer.lane_offset(num_lanes));
+                }
+            }
+            // Racers with a body are wherever its center of mass got to;
+            // any without one drive as in Race
+            GameMode::PhysicsRace => {
+                for racer in racers.iter_mut() {
+                    match racer.body.as_ref().map(|body| body.progress()) {
+                        Some((distance, height)) => {
+                            racer.stats.distance = distance;
+                            racer.y = height;
+                        }
+                        None => racer.advance(delta),
+                    }
+                    (racer.x, racer.z, racer.rotation_y) = track.position(racer.stats.distance, racer.lane_offset(num_lanes));
+                }
+            }
+            // A finished run stays where it crossed the line
+            GameMode::TimeTrial | GameMode::Laps => {
+                for racer in racers.iter_mut().filter(|r| r.stats.finish_time.is_none()) {
+                    racer.advance(delta);
+                    (racer.x, racer.z, racer.rotation_y) = track.position(racer.stats.distance, racer.lane_offset(num_lanes));
+                }
+            }
+            GameMode::TugOfWar => {
+                if let Some(rope) = rope {
+                    rope.step(racers, delta);
+                }
+            }
+        }
+    }
+
+    // Index of the leading racer once anyone has finished, the lowest lane
+    // on a dead heat. Finish times are recorded by RaceRules.
+    pub fn winner(self, racers: &[Racer]) -> Option<usize> {
+        match self {
+            GameMode::TugOfWar => {
+                let marker = Rope::marker(racers);
+                if marker <= -TUG_WIN_OFFSET {
+                    Some(0)
+                } else if marker >= TUG_WIN_OFFSET {
+                    Some(1)
+                } else {
+                    None
+                }
+            }
+            _ => racers.iter().any(|r| r.stats.finish_time.is_some()).then(|| Self::standings_by_time(racers)[0].0),
+        }
+    }
+
+    // Whether the game is over at race time `now`: the rope is won, or
+    // everyone is over the line, or FINISH_TIMEOUT has run out on those who
+    // are not
+    pub fn is_over(self, racers: &[Racer], now: f64) -> bool {
+        match self {
+            GameMode::TugOfWar => self.winner(racers).is_some(),
+            _ => {
+                let first = racers.iter().filter_map(|r| r.stats.finish_time).min_by(f64::total_cmp);
+                first.is_some_and(|first| racers.iter().all(|r| r.stats.finish_time.is_some()) || now >= first + FINISH_TIMEOUT)
+            }
+        }
+    }
+
+    // Every racer, best first, with their place counting from 1. Racers in a
+    // dead heat share a place and the next place is skipped; DNFs come last
+    // with place 0.
+    pub fn standings(self, racers: &[Racer]) -> Vec<(usize, u32)> {
+        match self {
+            GameMode::TugOfWar => match self.winner(racers) {
+                Some(index) => vec![(index, 1), (1 - index, 2)],
+                None => vec![(0, 1), (1, 1)],
+            },
+            _ => Self::standings_by_time(racers),
+        }
+    }
+
+    // Finishers by their interpolated finish time, then everyone else by how
+    // far they got, which is only a place while they are still racing. Ties
+    // stay in lane order.
+    fn standings_by_time(racers: &[Racer]) -> Vec<(usize, u32)> {
+        let key = |r: &Racer| (r.stats.finish_time, r.laps.progress(), r.laps.distance);
+        let tied = |a: &Racer, b: &Racer| match (key(a), key(b)) {
+            ((Some(a), _, _), (Some(b), _, _)) => (a - b).abs() <= DEAD_HEAT_TIME,
+            ((None, a_laps, a_distance), (None, b_laps, b_distance)) => a_laps == b_laps && a_distance == b_distance,
+            _ => false,
+        };
+
+        let mut order: Vec<usize> = (0..racers.len()).collect();
+        order.sort_by(|&a, &b| match (key(&racers[a]), key(&racers[b])) {
+            ((Some(a), _, _), (Some(b), _, _)) => a.total_cmp(&b),
+            ((Some(_), _, _), (None, _, _)) => Ordering::Less,
+            ((None, _, _), (Some(_), _, _)) => Ordering::Greater,
+            ((None, a_laps, a_distance), (None, b_laps, b_distance)) => b_laps.total_cmp(&a_laps).then(b_distance.total_cmp(&a_distance)),
+        });
+
+        let mut standings: Vec<(usize, u32)> = Vec::with_capacity(order.len());
+        for (position, &index) in order.iter().enumerate() {
+            let place = match standings.last() {
+                _ if racers[index].stats.dnf => 0,
+                Some(&(previous, place)) if tied(&racers[previous], &racers[index]) => place,
+                _ => position as u32 + 1,
+            };
+            standings.push((index, place));
+        }
+        standings
+    }
+}
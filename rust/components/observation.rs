@@ -0,0 +1,153 @@
+// Port of python/attn/frame_projection.py and data_utils.py: turns raw mesh
+// positions/velocities into the body-frame inputs the attention policy expects.
+
+use serde::{Deserialize, Serialize};
+use crate::components::policy::PolicyInput;
+
+// data/agents/<name>/policy.json
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PolicyMetadata {
+    pub center_vertex_id: usize,
+    pub forward_vertex_id: usize,
+    pub min_a: f32,
+    pub max_abs_da: f32,
+}
+
+impl PolicyMetadata {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("policy.json: {}", e))
+    }
+
+    // The policy outputs da; as in generate_trajectory_with_attn_policy.py it
+    // is clamped to max_abs_da and the result kept within [min_a, 1].
+    pub fn apply(&self, a: &mut [f32], da: &[f32]) {
+        for (a, &da) in a.iter_mut().zip(da.iter()) {
+            *a = (*a + da.clamp(-self.max_abs_da, self.max_abs_da)).clamp(self.min_a, 1.0);
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MeshData {
+    pub pos: Vec<[f32; 2]>,
+    pub muscles: Vec<[usize; 2]>,
+    // Per vertex, across the side view; flat meshes have none
+    #[serde(default)]
+    pub depth: Vec<f32>,
+}
+
+impl MeshData {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("mesh.json: {}", e))
+    }
+}
+
+// Body frame: x along center -> forward vertex, y perpendicular to it
+fn frame(pos: &[[f32; 2]], center_vertex_id: usize, forward_vertex_id: usize) -> ([f32; 2], [f32; 2], [f32; 2]) {
+    let c = pos[center_vertex_id];
+    let f = pos[forward_vertex_id];
+    let (dx, dy) = (f[0] - c[0], f[1] - c[1]);
+    let q = dx * dx + dy * dy;
+    let a = if q == 0.0 { [1.0, 0.0] } else { [dx / q.sqrt(), dy / q.sqrt()] };
+    let b = [-a[1], a[0]];
+    (c, a, b)
+}
+
+pub fn frame_projection(pos: &[[f32; 2]], center_vertex_id: usize, forward_vertex_id: usize, data: &[[f32; 2]], subtract_origin: bool) -> Vec<[f32; 2]> {
+    let (c, a, b) = frame(pos, center_vertex_id, forward_vertex_id);
+    data.iter()
+        .map(|p| {
+            let (px, py) = if subtract_origin { (p[0] - c[0], p[1] - c[1]) } else { (p[0], p[1]) };
+            [a[0] * px + a[1] * py, b[0] * px + b[1] * py]
+        })
+        .collect()
+}
+
+pub fn project_pos_vel(pos: &[[f32; 2]], vel: &[[f32; 2]], center_vertex_id: usize, forward_vertex_id: usize) -> (Vec<[f32; 2]>, Vec<[f32; 2]>) {
+    (
+        frame_projection(pos, center_vertex_id, forward_vertex_id, pos, true),
+        frame_projection(pos, center_vertex_id, forward_vertex_id, vel, false),
+    )
+}
+
+// Vertex and muscle keys are computed once from the rest pose; only the
+// vertex values change from step to step.
+#[derive(Debug, Clone)]
+pub struct ObservationBuilder {
+    pub center_vertex_id: usize,
+    pub forward_vertex_id: usize,
+    pub vertex_k: Vec<f32>,
+    pub muscle_k: Vec<f32>,
+    pub muscle_depth: Vec<f32>, // rest midpoints, empty for flat meshes
+    pub num_vertices: usize,
+    pub num_muscles: usize,
+}
+
+impl ObservationBuilder {
+    pub fn new(mesh: &MeshData, center_vertex_id: usize, forward_vertex_id: usize) -> Self {
+        let vel = vec![[0.0, 0.0]; mesh.pos.len()];
+        let (projected_pos, _) = project_pos_vel(&mesh.pos, &vel, center_vertex_id, forward_vertex_id);
+
+        let muscle_k = mesh
+            .muscles
+            .iter()
+            .flat_map(|&[i1, i2]| {
+                let (p1, p2) = (projected_pos[i1], projected_pos[i2]);
+                [(p1[0] + p2[0]) / 2.0, (p1[1] + p2[1]) / 2.0]
+            })
+            .collect();
+        let muscle_depth = match mesh.depth.len() == mesh.pos.len() {
+            true => mesh.muscles.iter().map(|&[i1, i2]| (mesh.depth[i1] + mesh.depth[i2]) / 2.0).collect(),
+            false => Vec::new(),
+        };
+
+        Self {
+            center_vertex_id,
+            forward_vertex_id,
+            vertex_k: projected_pos.iter().flatten().copied().collect(),
+            muscle_k,
+            muscle_depth,
+            num_vertices: mesh.pos.len(),
+            num_muscles: mesh.muscles.len(),
+        }
+    }
+
+    pub fn from_json(mesh_json: &str, policy_json: &str) -> Result<Self, String> {
+        let mesh = MeshData::from_json(mesh_json)?;
+        let meta = PolicyMetadata::from_json(policy_json)?;
+        Ok(Self::new(&mesh, meta.center_vertex_id, meta.forward_vertex_id))
+    }
+
+    pub fn build(&self, pos: &[[f32; 2]], vel: &[[f32; 2]]) -> PolicyInput {
+        let (projected_pos, projected_vel) = project_pos_vel(pos, vel, self.center_vertex_id, self.forward_vertex_id);
+        let vertex_v = projected_pos
+            .iter()
+            .zip(projected_vel.iter())
+            .flat_map(|(p, v)| [p[0], p[1], v[0], v[1]])
+            .collect();
+
+        PolicyInput {
+            vertex_k: self.vertex_k.clone(),
+            muscle_k: self.muscle_k.clone(),
+            vertex_v,
+            num_vertices: self.num_vertices,
+            num_muscles: self.num_muscles,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Forward points along +y, so world +y becomes body +x and world +x body -y
+    #[test]
+    fn test_frame_projection() {
+        let pos = [[1.0, 1.0], [1.0, 2.0], [2.0, 1.0]];
+        let projected = frame_projection(&pos, 0, 1, &pos, true);
+        let expected = [[0.0, 0.0], [1.0, 0.0], [0.0, -1.0]];
+        for (p, e) in projected.iter().zip(expected.iter()) {
+            assert!((p[0] - e[0]).abs() < 1e-6 && (p[1] - e[1]).abs() < 1e-6, "{:?} != {:?}", p, e);
+        }
+    }
+}
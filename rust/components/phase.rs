@@ -0,0 +1,122 @@
+use wasm_bindgen::prelude::*;
+use crate::components::clock::{TickClock, TICK_RATE};
+use crate::components::racer::Racer;
+
+// Lights before the go, in seconds
+pub const DEFAULT_COUNTDOWN: f32 = 3.0;
+pub const MAX_COUNTDOWN: f32 = 10.0;
+
+// Throttle past this during the countdown jumps the start, and holds the
+// racer at the line this long after the go
+pub const FALSE_START_THRESHOLD: f32 = 0.1;
+pub const FALSE_START_PENALTY: f32 = 1.0;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RacePhase {
+    #[default]
+    Lobby = 0,     // before start_game
+    Countdown = 1, // nobody moves; throttle is a false start
+    Racing = 2,
+    Finished = 3,  // per racer only: over the line, the race goes on
+    Results = 4,   // decided, nothing moves any more
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phases {
+    pub phase: RacePhase,
+    pub countdown: u64,  // configured length, in ticks
+    pub remaining: u64,  // ticks left in this countdown
+    pub race_start: u64, // clock tick the race went green on
+}
+
+impl Default for Phases {
+    fn default() -> Self {
+        let countdown = Self::to_ticks(DEFAULT_COUNTDOWN);
+        Self { phase: RacePhase::Lobby, countdown, remaining: countdown, race_start: 0 }
+    }
+}
+
+impl Phases {
+    fn to_ticks(seconds: f32) -> u64 {
+        (seconds * TICK_RATE as f32).round() as u64
+    }
+
+    // Takes effect from the next start; 0 goes straight to racing
+    pub fn set_countdown(&mut self, seconds: f32) -> Result<(), String> {
+        if !(0.0..=MAX_COUNTDOWN).contains(&seconds) {
+            return Err(format!("countdown of {} s is outside 0 to {} s", seconds, MAX_COUNTDOWN));
+        }
+        self.countdown = Self::to_ticks(seconds);
+        Ok(())
+    }
+
+    pub fn start(&mut self) {
+        self.remaining = self.countdown;
+        self.race_start = 0;
+        self.phase = if self.countdown == 0 { RacePhase::Racing } else { RacePhase::Countdown };
+    }
+
+    // One countdown tick at clock tick `tick`; the race goes green once
+    // none are left
+    pub fn count_down(&mut self, tick: u64) {
+        self.remaining = self.remaining.saturating_sub(1);
+        if self.remaining == 0 {
+            self.phase = RacePhase::Racing;
+            self.race_start = tick;
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.phase, RacePhase::Countdown | RacePhase::Racing)
+    }
+
+    pub fn countdown_seconds(&self) -> f32 {
+        if self.phase == RacePhase::Countdown {
+            TickClock::seconds(self.remaining) as f32
+        } else {
+            0.0
+        }
+    }
+
+    // Race time at clock tick `tick`, in seconds from the go
+    pub fn race_time(&self, tick: u64) -> f64 {
+        TickClock::seconds(tick.saturating_sub(self.race_start))
+    }
+
+    pub fn racer_phase(&self, racer: &Racer) -> RacePhase {
+        if self.phase == RacePhase::Racing && racer.stats.finish_time.is_some() {
+            RacePhase::Finished
+        } else {
+            self.phase
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_countdown() {
+        let mut phases = Phases::default();
+        assert!(phases.set_countdown(-1.0).is_err());
+        assert!(phases.set_countdown(f32::NAN).is_err());
+        phases.set_countdown(0.5).unwrap();
+        phases.start();
+        assert_eq!(phases.phase, RacePhase::Countdown);
+        assert_eq!(phases.countdown_seconds(), 0.5);
+        for tick in 1..=30 {
+            assert_eq!(phases.phase, RacePhase::Countdown);
+            phases.count_down(tick);
+        }
+        assert_eq!(phases.phase, RacePhase::Racing);
+        assert_eq!(phases.race_start, 30);
+        assert_eq!(phases.race_time(90), 1.0);
+
+        phases.set_countdown(0.0).unwrap();
+        phases.start();
+        assert_eq!(phases.phase, RacePhase::Racing);
+        assert_eq!(phases.race_time(0), 0.0);
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::components::tensor::{Tensor, tensors_from_json};
+use crate::components::weights_bin;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AttentionWeights {
@@ -7,31 +9,253 @@ pub struct AttentionWeights {
     pub layers: HashMap<String, Vec<f32>>,
 }
 
+// Parameter names in the order PyTorch's state_dict() lists them
+pub const WEIGHT_NAMES: [&str; 8] = [
+    "muscle_k_to_vertex_q.0.weight",
+    "muscle_k_to_vertex_q.0.bias",
+    "muscle_k_to_vertex_q.2.weight",
+    "muscle_k_to_vertex_q.2.bias",
+    "wv_to_output.0.weight",
+    "wv_to_output.0.bias",
+    "wv_to_output.2.weight",
+    "wv_to_output.2.bias",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttentionDims {
+    pub vertex_key_size: usize,
+    pub vertex_value_size: usize,
+    pub muscle_key_size: usize,
+    pub num_heads: usize,
+    pub num_blocks: usize,
+    pub muscle_self_attention: bool,
+}
+
+impl AttentionDims {
+    // Shapes as in the PyTorch state_dict and in its order: WEIGHT_NAMES,
+    // then blocks.{i}.{0,2}.* for the extra blocks, then muscle_self_*.
+    pub fn parameter_shapes(&self) -> Vec<(String, Vec<usize>)> {
+        let q_size = self.num_heads * self.vertex_key_size;
+        let wv_size = self.num_heads * self.vertex_value_size;
+        let base = [
+            vec![HIDDEN_SIZE, self.muscle_key_size],
+            vec![HIDDEN_SIZE],
+            vec![q_size, HIDDEN_SIZE],
+            vec![q_size],
+            vec![HIDDEN_SIZE, wv_size],
+            vec![HIDDEN_SIZE],
+            vec![1, HIDDEN_SIZE],
+            vec![1],
+        ];
+        let mut shapes: Vec<(String, Vec<usize>)> = WEIGHT_NAMES.iter().map(|name| name.to_string()).zip(base).collect();
+
+        for i in 0..self.num_blocks.saturating_sub(1) {
+            shapes.push((format!("blocks.{}.0.weight", i), vec![HIDDEN_SIZE, wv_size + self.muscle_key_size]));
+            shapes.push((format!("blocks.{}.0.bias", i), vec![HIDDEN_SIZE]));
+            shapes.push((format!("blocks.{}.2.weight", i), vec![q_size, HIDDEN_SIZE]));
+            shapes.push((format!("blocks.{}.2.bias", i), vec![q_size]));
+        }
+
+        if self.muscle_self_attention {
+            for (name, out_features) in [("muscle_self_q", q_size), ("muscle_self_k", self.vertex_key_size), ("muscle_self_v", self.vertex_value_size)] {
+                shapes.push((format!("{}.weight", name), vec![out_features, wv_size]));
+                shapes.push((format!("{}.bias", name), vec![out_features]));
+            }
+        }
+        shapes
+    }
+}
+
+// One evaluation's worth of inputs, flattened row-major as forward() expects
+#[derive(Debug, Clone)]
+pub struct PolicyInput {
+    pub vertex_k: Vec<f32>,
+    pub muscle_k: Vec<f32>,
+    pub vertex_v: Vec<f32>,
+    pub num_vertices: usize,
+    pub num_muscles: usize,
+}
+
+// Storage-dependent part of the model. forward_with() is written once against
+// this so the f32 model and its quantized variant cannot drift apart.
+pub(crate) trait Layers {
+    fn dims(&self) -> AttentionDims;
+    fn linear(&self, input: &[f32], weight_name: &str, bias_name: &str, in_features: usize, out_features: usize) -> Vec<f32>;
+}
+
+// Width of the hidden layer in both MLPs (fixed in attn.Model)
+pub const HIDDEN_SIZE: usize = 32;
+
 pub struct AttentionModel {
     pub vertex_key_size: usize,
     pub vertex_value_size: usize,
     pub muscle_key_size: usize,
     pub num_heads: usize,
-    pub weights: HashMap<String, Vec<f32>>,
+    pub num_blocks: usize,
+    pub muscle_self_attention: bool,
+    pub weights: HashMap<String, Tensor>,
 }
 
 impl AttentionModel {
     pub fn new(args_json: &str, weights_json: &str) -> Self {
-        let args: serde_json::Value = serde_json::from_str(args_json).unwrap();
-        let weights: HashMap<String, Vec<f32>> = serde_json::from_str(weights_json).unwrap();
-
-        Self {
-            vertex_key_size: args["vertex_key_size"].as_u64().unwrap() as usize,
-            vertex_value_size: args["vertex_value_size"].as_u64().unwrap() as usize,
-            muscle_key_size: args["muscle_key_size"].as_u64().unwrap() as usize,
-            num_heads: args["num_heads"].as_u64().unwrap() as usize,
+        Self::try_new(args_json, weights_json).unwrap()
+    }
+
+    pub fn try_new(args_json: &str, weights_json: &str) -> Result<Self, String> {
+        let weights = tensors_from_json(weights_json).map_err(|e| format!("weights.json: {}", e))?;
+        Self::from_tensors(args_json, weights)
+    }
+
+    pub fn from_bin(args_json: &str, bytes: &[u8]) -> Result<Self, String> {
+        let weights = weights_bin::read(bytes)?.to_tensors();
+        Self::from_tensors(args_json, weights)
+    }
+
+    pub fn from_tensors(args_json: &str, weights: HashMap<String, Tensor>) -> Result<Self, String> {
+        let args: serde_json::Value = serde_json::from_str(args_json).map_err(|e| format!("args.json: {}", e))?;
+
+        let arg = |name: &str| {
+            args[name]
+                .as_u64()
+                .map(|v| v as usize)
+                .ok_or_else(|| format!("args.json: missing {}", name))
+        };
+
+        // Optional, absent from single-block checkpoints
+        let num_blocks = match &args["num_blocks"] {
+            serde_json::Value::Null => 1,
+            _ => arg("num_blocks")?,
+        };
+        if num_blocks == 0 {
+            return Err("args.json: num_blocks must be at least 1".to_string());
+        }
+
+        let model = Self {
+            vertex_key_size: arg("vertex_key_size")?,
+            vertex_value_size: arg("vertex_value_size")?,
+            muscle_key_size: arg("muscle_key_size")?,
+            num_heads: arg("num_heads")?,
+            num_blocks,
+            muscle_self_attention: args["muscle_self_attention"].as_bool().unwrap_or(false),
             weights,
+        };
+
+        for (name, shape) in model.parameter_shapes() {
+            let tensor = model.weights.get(&name).ok_or_else(|| format!("weights: missing {}", name))?;
+            if tensor.len() != shape.iter().product::<usize>() {
+                return Err(format!("weights: {} has shape {:?}, expected {:?}", name, tensor.shape, shape));
+            }
+        }
+
+        Ok(model)
+    }
+
+    pub fn parameter_shapes(&self) -> Vec<(String, Vec<usize>)> {
+        self.dims().parameter_shapes()
+    }
+
+    fn relu(input: &mut [f32]) {
+        for val in input.iter_mut() {
+            if *val < 0.0 {
+                *val = 0.0;
+            }
+        }
+    }
+
+    fn tanh(input: &mut [f32]) {
+        for val in input.iter_mut() {
+            *val = val.tanh();
+        }
+    }
+
+    // All parameters as one flat vector, in parameter_shapes() order
+    pub fn parameters(&self) -> Vec<f32> {
+        self.parameter_shapes().iter().flat_map(|(name, _)| self.weights[name].data.iter().copied()).collect()
+    }
+
+    pub fn set_parameters(&mut self, params: &[f32]) {
+        let mut offset = 0;
+        for (name, _) in self.parameter_shapes() {
+            let tensor = self.weights.get_mut(&name).unwrap();
+            let len = tensor.len();
+            tensor.data.copy_from_slice(&params[offset..offset + len]);
+            offset += len;
+        }
+        assert_eq!(offset, params.len(), "parameter count mismatch");
+    }
+
+    // args.json as written by attn.Model.save (json.dump with indent=2)
+    pub fn args_json(&self) -> String {
+        let mut fields = vec![
+            format!("\"vertex_key_size\": {}", self.vertex_key_size),
+            format!("\"vertex_value_size\": {}", self.vertex_value_size),
+            format!("\"muscle_key_size\": {}", self.muscle_key_size),
+            format!("\"num_heads\": {}", self.num_heads),
+        ];
+        // Like Model.save, only the deeper variants mention these
+        if self.num_blocks != 1 {
+            fields.push(format!("\"num_blocks\": {}", self.num_blocks));
+        }
+        if self.muscle_self_attention {
+            fields.push("\"muscle_self_attention\": true".to_string());
+        }
+        format!("{{\n  {}\n}}", fields.join(",\n  "))
+    }
+
+    // weights.json as written by python/scripts/export_weights.py, byte for byte
+    pub fn weights_json(&self) -> String {
+        let mut out = String::from("{");
+        for (i, (name, _)) in self.parameter_shapes().iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            out.push_str(&format!("\"{}\": ", name));
+            self.weights[name].write_python_json(&mut out);
         }
+        out.push('}');
+        out
+    }
+
+    // weights.bin, tensors in the same order as weights.json
+    pub fn weights_bin(&self) -> Vec<u8> {
+        let shapes = self.parameter_shapes();
+        let tensors: Vec<(&str, &Tensor)> = shapes.iter().map(|(name, _)| (name.as_str(), &self.weights[name])).collect();
+        weights_bin::write(&tensors)
+    }
+
+    // (args.json, weights.json)
+    pub fn to_json(&self) -> (String, String) {
+        (self.args_json(), self.weights_json())
+    }
+
+    pub fn dims(&self) -> AttentionDims {
+        AttentionDims {
+            vertex_key_size: self.vertex_key_size,
+            vertex_value_size: self.vertex_value_size,
+            muscle_key_size: self.muscle_key_size,
+            num_heads: self.num_heads,
+            num_blocks: self.num_blocks,
+            muscle_self_attention: self.muscle_self_attention,
+        }
+    }
+
+    pub fn forward(&self, vertex_k: &[f32], muscle_k: &[f32], vertex_v: &[f32], num_vertices: usize, num_muscles: usize) -> Vec<f32> {
+        forward_with(self, vertex_k, muscle_k, vertex_v, num_vertices, num_muscles)
+    }
+
+    pub fn forward_input(&self, input: &PolicyInput) -> Vec<f32> {
+        self.forward(&input.vertex_k, &input.muscle_k, &input.vertex_v, input.num_vertices, input.num_muscles)
+    }
+}
+
+impl Layers for AttentionModel {
+    fn dims(&self) -> AttentionDims {
+        AttentionModel::dims(self)
     }
 
     fn linear(&self, input: &[f32], weight_name: &str, bias_name: &str, in_features: usize, out_features: usize) -> Vec<f32> {
-        let weight = &self.weights[weight_name];
-        let bias = &self.weights[bias_name];
+        let weight = &self.weights[weight_name].data;
+        let bias = &self.weights[bias_name].data;
         let mut output = vec![0.0; out_features];
 
         for i in 0..out_features {
@@ -43,81 +267,188 @@ impl AttentionModel {
         }
         output
     }
+}
 
-    fn relu(input: &mut [f32]) {
-        for val in input.iter_mut() {
-            if *val < 0.0 {
-                *val = 0.0;
+// Linear -> ReLU -> Linear, the layout of every MLP in attn.Model
+fn mlp<M: Layers>(model: &M, input: &[f32], prefix: &str, in_features: usize, out_features: usize) -> Vec<f32> {
+    let mut x = model.linear(input, &format!("{}.0.weight", prefix), &format!("{}.0.bias", prefix), in_features, HIDDEN_SIZE);
+    AttentionModel::relu(&mut x);
+    model.linear(&x, &format!("{}.2.weight", prefix), &format!("{}.2.bias", prefix), HIDDEN_SIZE, out_features)
+}
+
+// vertex_attention.py for one query: per head, softmax(q_h . k) over the
+// keys weights the values. Keys are shared by all heads.
+fn attend(q_all_heads: &[f32], keys: &[f32], values: &[f32], num_keys: usize, num_heads: usize, key_size: usize, value_size: usize) -> Vec<f32> {
+    let mut head_outputs = vec![0.0; num_heads * value_size];
+
+    for h in 0..num_heads {
+        let q_head = &q_all_heads[h * key_size..(h + 1) * key_size];
+
+        let mut attention_scores = vec![0.0; num_keys];
+        let mut max_score = f32::NEG_INFINITY;
+
+        for v in 0..num_keys {
+            let k_v = &keys[v * key_size..(v + 1) * key_size];
+            let mut score = 0.0;
+            for i in 0..key_size {
+                score += q_head[i] * k_v[i];
+            }
+            attention_scores[v] = score;
+            if score > max_score {
+                max_score = score;
+            }
+        }
+
+        // Softmax
+        let mut sum_exp = 0.0;
+        for score in attention_scores.iter_mut() {
+            *score = (*score - max_score).exp();
+            sum_exp += *score;
+        }
+        for score in attention_scores.iter_mut() {
+            *score /= sum_exp;
+        }
+
+        // Weighted sum of values
+        for v in 0..num_keys {
+            let v_v = &values[v * value_size..(v + 1) * value_size];
+            for i in 0..value_size {
+                head_outputs[h * value_size + i] += attention_scores[v] * v_v[i];
             }
         }
     }
 
-    fn tanh(input: &mut [f32]) {
-        for val in input.iter_mut() {
-            *val = val.tanh();
+    head_outputs
+}
+
+pub(crate) fn forward_with<M: Layers>(model: &M, vertex_k: &[f32], muscle_k: &[f32], vertex_v: &[f32], num_vertices: usize, num_muscles: usize) -> Vec<f32> {
+    let dims = model.dims();
+    let q_size = dims.num_heads * dims.vertex_key_size;
+    let wv_size = dims.num_heads * dims.vertex_value_size;
+    let mut features = Vec::with_capacity(num_muscles);
+
+    for m in 0..num_muscles {
+        // 1. muscle_k_to_vertex_q
+        let m_k = &muscle_k[m * dims.muscle_key_size..(m + 1) * dims.muscle_key_size];
+        let q_all_heads = mlp(model, m_k, "muscle_k_to_vertex_q", dims.muscle_key_size, q_size);
+
+        // 2. Attention
+        let mut head_outputs = attend(&q_all_heads, vertex_k, vertex_v, num_vertices, dims.num_heads, dims.vertex_key_size, dims.vertex_value_size);
+
+        // Extra blocks add what they read to the running head outputs
+        for block in 0..dims.num_blocks - 1 {
+            let input = [&head_outputs[..], m_k].concat();
+            let q = mlp(model, &input, &format!("blocks.{}", block), wv_size + dims.muscle_key_size, q_size);
+            let read = attend(&q, vertex_k, vertex_v, num_vertices, dims.num_heads, dims.vertex_key_size, dims.vertex_value_size);
+            for (x, r) in head_outputs.iter_mut().zip(read) {
+                *x += r;
+            }
         }
+        features.push(head_outputs);
     }
 
-    pub fn forward(&self, vertex_k: &[f32], muscle_k: &[f32], vertex_v: &[f32], num_vertices: usize, num_muscles: usize) -> Vec<f32> {
-        let mut muscle_activations = Vec::with_capacity(num_muscles);
-
-        for m in 0..num_muscles {
-            // 1. muscle_k_to_vertex_q
-            let m_k = &muscle_k[m * self.muscle_key_size..(m + 1) * self.muscle_key_size];
-            
-            let mut x = self.linear(m_k, "muscle_k_to_vertex_q.0.weight", "muscle_k_to_vertex_q.0.bias", self.muscle_key_size, 32);
-            Self::relu(&mut x);
-            let q_all_heads = self.linear(&x, "muscle_k_to_vertex_q.2.weight", "muscle_k_to_vertex_q.2.bias", 32, self.num_heads * self.vertex_key_size);
-
-            // 2. Attention
-            let mut head_outputs = vec![0.0; self.num_heads * self.vertex_value_size];
-            
-            for h in 0..self.num_heads {
-                let q_head = &q_all_heads[h * self.vertex_key_size..(h + 1) * self.vertex_key_size];
-                
-                let mut attention_scores = vec![0.0; num_vertices];
-                let mut max_score = f32::NEG_INFINITY;
-
-                for v in 0..num_vertices {
-                    let k_v = &vertex_k[v * self.vertex_key_size..(v + 1) * self.vertex_key_size];
-                    let mut score = 0.0;
-                    for i in 0..self.vertex_key_size {
-                        score += q_head[i] * k_v[i];
-                    }
-                    attention_scores[v] = score;
-                    if score > max_score {
-                        max_score = score;
-                    }
-                }
-
-                // Softmax
-                let mut sum_exp = 0.0;
-                for score in attention_scores.iter_mut() {
-                    *score = (*score - max_score).exp();
-                    sum_exp += *score;
-                }
-                for score in attention_scores.iter_mut() {
-                    *score /= sum_exp;
-                }
-
-                // Weighted sum of vertex values
-                for v in 0..num_vertices {
-                    let v_v = &vertex_v[v * self.vertex_value_size..(v + 1) * self.vertex_value_size];
-                    for i in 0..self.vertex_value_size {
-                        head_outputs[h * self.vertex_value_size + i] += attention_scores[v] * v_v[i];
-                    }
-                }
+    // Muscle-to-muscle coordination, same attention over the other muscles
+    if dims.muscle_self_attention {
+        let project = |name: &str, out_features: usize| -> Vec<Vec<f32>> {
+            let (weight, bias) = (format!("{}.weight", name), format!("{}.bias", name));
+            features.iter().map(|f| model.linear(f, &weight, &bias, wv_size, out_features)).collect()
+        };
+        let queries = project("muscle_self_q", q_size);
+        let keys = project("muscle_self_k", dims.vertex_key_size).concat();
+        let values = project("muscle_self_v", dims.vertex_value_size).concat();
+
+        for (f, q) in features.iter_mut().zip(queries.iter()) {
+            let read = attend(q, &keys, &values, num_muscles, dims.num_heads, dims.vertex_key_size, dims.vertex_value_size);
+            for (x, r) in f.iter_mut().zip(read) {
+                *x += r;
             }
+        }
+    }
+
+    // 3. wv_to_output
+    features
+        .iter()
+        .map(|head_outputs| {
+            let mut y = model.linear(head_outputs, "wv_to_output.0.weight", "wv_to_output.0.bias", wv_size, HIDDEN_SIZE);
+            AttentionModel::relu(&mut y);
+            let mut output = model.linear(&y, "wv_to_output.2.weight", "wv_to_output.2.bias", HIDDEN_SIZE, 1);
+            AttentionModel::tanh(&mut output);
+            output[0]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::rng::Rng;
+
+    const ARGS_JSON: &str = include_str!("../../data/policies/attn/args.json");
+    const WEIGHTS_JSON: &str = include_str!("../../data/policies/attn/weights.json");
+
+    // A deeper checkpoint with random weights on top of the trained base ones
+    fn variant(num_blocks: usize, muscle_self_attention: bool) -> AttentionModel {
+        let base = AttentionModel::try_new(ARGS_JSON, WEIGHTS_JSON).unwrap();
+        let dims = AttentionDims { num_blocks, muscle_self_attention, ..base.dims() };
+        let mut rng = Rng::new(1);
+        let mut weights = base.weights.clone();
+        for (name, shape) in dims.parameter_shapes() {
+            weights.entry(name).or_insert_with(|| {
+                let len = shape.iter().product();
+                Tensor::new(shape, (0..len).map(|_| 0.3 * rng.gaussian()).collect())
+            });
+        }
+        let base = AttentionModel { weights: HashMap::new(), num_blocks, muscle_self_attention, ..base };
+        AttentionModel::from_tensors(&base.args_json(), weights).unwrap()
+    }
 
-            // 3. wv_to_output
-            let mut y = self.linear(&head_outputs, "wv_to_output.0.weight", "wv_to_output.0.bias", self.num_heads * self.vertex_value_size, 32);
-            Self::relu(&mut y);
-            let mut output = self.linear(&y, "wv_to_output.2.weight", "wv_to_output.2.bias", 32, 1);
-            Self::tanh(&mut output);
-            
-            muscle_activations.push(output[0]);
+    fn random_input(rng: &mut Rng) -> PolicyInput {
+        let (num_vertices, num_muscles) = (5, 3);
+        let mut values = |n: usize| (0..n).map(|_| 0.1 * rng.gaussian()).collect::<Vec<f32>>();
+        PolicyInput {
+            vertex_k: values(num_vertices * 2),
+            muscle_k: values(num_muscles * 2),
+            vertex_v: values(num_vertices * 4),
+            num_vertices,
+            num_muscles,
         }
+    }
+
+    #[test]
+    fn test_variants_round_trip() {
+        let model = variant(3, true);
+        let (args_json, weights_json) = model.to_json();
+        assert!(args_json.ends_with("\"num_heads\": 20,\n  \"num_blocks\": 3,\n  \"muscle_self_attention\": true\n}"));
+        assert!(weights_json.contains("\"blocks.1.2.bias\": ["));
+
+        let reloaded = AttentionModel::try_new(&args_json, &weights_json).unwrap();
+        assert_eq!(reloaded.parameters(), model.parameters());
+        let input = random_input(&mut Rng::new(2));
+        assert_eq!(reloaded.forward_input(&input), model.forward_input(&input));
+
+        // Checkpoint and args.json must agree on the architecture
+        assert!(AttentionModel::try_new(&args_json, WEIGHTS_JSON).is_err());
+    }
+
+    #[test]
+    fn test_self_attention_couples_muscles() {
+        let mut rng = Rng::new(3);
+        let input = random_input(&mut rng);
+        let mut moved = input.clone();
+        moved.muscle_k[4] += 1.0; // muscle 2
+
+        let blocks = variant(2, false);
+        assert_eq!(blocks.forward_input(&input)[0], blocks.forward_input(&moved)[0]);
+
+        let coupled = variant(1, true);
+        assert_ne!(coupled.forward_input(&input)[0], coupled.forward_input(&moved)[0]);
+    }
 
-        muscle_activations
+    #[test]
+    fn test_to_json_matches_python_export() {
+        let model = AttentionModel::try_new(ARGS_JSON, WEIGHTS_JSON).unwrap();
+        let (args_json, weights_json) = model.to_json();
+        assert_eq!(args_json, ARGS_JSON);
+        assert!(weights_json == WEIGHTS_JSON, "weights.json differs from the Python export");
     }
 }
@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use crate::components::policy::{forward_with, AttentionDims, AttentionModel, Layers, PolicyInput};
+
+// Only F16 keeps the attention policy within its error budget (see
+// test_quantized_error_report); the int8 modes are there for comparison.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantization {
+    Int8PerTensor,
+    Int8PerRow,
+    F16,
+}
+
+impl Quantization {
+    pub fn from_u8(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Quantization::Int8PerTensor),
+            1 => Some(Quantization::Int8PerRow),
+            2 => Some(Quantization::F16),
+            _ => None,
+        }
+    }
+}
+
+// Weight matrices are quantized; biases are tiny and stay f32.
+#[derive(Debug, Clone)]
+enum QuantizedWeight {
+    Int8 { values: Vec<i8>, scales: Vec<f32> }, // one scale per row, or a single one
+    F16 { values: Vec<u16> },
+}
+
+impl QuantizedWeight {
+    fn quantize(data: &[f32], row_len: usize, mode: Quantization) -> Self {
+        match mode {
+            Quantization::F16 => QuantizedWeight::F16 { values: data.iter().map(|&w| f32_to_f16(w)).collect() },
+            Quantization::Int8PerTensor | Quantization::Int8PerRow => {
+                let group_len = if mode == Quantization::Int8PerRow { row_len } else { data.len() };
+                let mut values = Vec::with_capacity(data.len());
+                let mut scales = Vec::new();
+                for group in data.chunks(group_len.max(1)) {
+                    let max_abs = group.iter().fold(0.0f32, |m, w| m.max(w.abs()));
+                    let scale = if max_abs > 0.0 { max_abs / 127.0 } else { 1.0 };
+                    values.extend(group.iter().map(|&w| (w / scale).round().clamp(-127.0, 127.0) as i8));
+                    scales.push(scale);
+                }
+                QuantizedWeight::Int8 { values, scales }
+            }
+        }
+    }
+
+    // Dot product of one weight row with `input`, accumulated in f32
+    fn dot_row(&self, row: usize, input: &[f32]) -> f32 {
+        let n = input.len();
+        match self {
+            QuantizedWeight::Int8 { values, scales } => {
+                let q = &values[row * n..(row + 1) * n];
+                let sum: f32 = q.iter().zip(input).map(|(&w, &x)| w as f32 * x).sum();
+                sum * scales[if scales.len() == 1 { 0 } else { row }]
+            }
+            QuantizedWeight::F16 { values } => {
+                let h = &values[row * n..(row + 1) * n];
+                h.iter().zip(input).map(|(&w, &x)| f16_to_f32(w) * x).sum()
+            }
+        }
+    }
+
+    fn size_bytes(&self) -> usize {
+        match self {
+            QuantizedWeight::Int8 { values, scales } => values.len() + scales.len() * 4,
+            QuantizedWeight::F16 { values } => values.len() * 2,
+        }
+    }
+}
+
+// Reduced-precision copy of an AttentionModel for low-end devices. Runs the
+// exact same forward pass; only the weight storage differs.
+#[derive(Debug, Clone)]
+pub struct QuantizedAttentionModel {
+    pub quantization: Quantization,
+    dims: AttentionDims,
+    weights: HashMap<String, QuantizedWeight>,
+    biases: HashMap<String, Vec<f32>>,
+}
+
+impl QuantizedAttentionModel {
+    pub fn from_model(model: &AttentionModel, quantization: Quantization) -> Self {
+        let mut weights = HashMap::new();
+        let mut biases = HashMap::new();
+
+        for (name, shape) in model.parameter_shapes() {
+            let data = &model.weights[&name].data;
+            if shape.len() == 2 {
+                weights.insert(name.clone(), QuantizedWeight::quantize(data, shape[1], quantization));
+            } else {
+                biases.insert(name, data.clone());
+            }
+        }
+
+        Self { quantization, dims: model.dims(), weights, biases }
+    }
+
+    pub fn try_new(args_json: &str, weights_json: &str, quantization: Quantization) -> Result<Self, String> {
+        Ok(Self::from_model(&AttentionModel::try_new(args_json, weights_json)?, quantization))
+    }
+
+    pub fn forward(&self, vertex_k: &[f32], muscle_k: &[f32], vertex_v: &[f32], num_vertices: usize, num_muscles: usize) -> Vec<f32> {
+        forward_with(self, vertex_k, muscle_k, vertex_v, num_vertices, num_muscles)
+    }
+
+    pub fn forward_input(&self, input: &PolicyInput) -> Vec<f32> {
+        self.forward(&input.vertex_k, &input.muscle_k, &input.vertex_v, input.num_vertices, input.num_muscles)
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.weights.values().map(QuantizedWeight::size_bytes).sum::<usize>()
+            + self.biases.values().map(|b| b.len() * 4).sum::<usize>()
+    }
+}
+
+impl Layers for QuantizedAttentionModel {
+    fn dims(&self) -> AttentionDims {
+        self.dims
+    }
+
+    fn linear(&self, input: &[f32], weight_name: &str, bias_name: &str, in_features: usize, out_features: usize) -> Vec<f32> {
+        let weight = &self.weights[weight_name];
+        let bias = &self.biases[bias_name];
+        let input = &input[..in_features];
+        (0..out_features).map(|i| bias[i] + weight.dot_row(i, input)).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorReport {
+    pub max_abs_error: f32,
+    pub mean_abs_error: f32,
+    pub samples: usize,
+}
+
+// Activation error of the quantized path against the f32 reference, over a set
+// of recorded observations.
+pub fn compare(reference: &AttentionModel, quantized: &QuantizedAttentionModel, observations: &[PolicyInput]) -> ErrorReport {
+    let mut max_abs_error = 0.0f32;
+    let mut total = 0.0f64;
+    let mut samples = 0;
+
+    for input in observations {
+        let expected = reference.forward_input(input);
+        let actual = quantized.forward_input(input);
+        for (a, b) in expected.iter().zip(actual.iter()) {
+            let err = (a - b).abs();
+            max_abs_error = max_abs_error.max(err);
+            total += err as f64;
+            samples += 1;
+        }
+    }
+
+    ErrorReport {
+        max_abs_error,
+        mean_abs_error: if samples > 0 { (total / samples as f64) as f32 } else { 0.0 },
+        samples,
+    }
+}
+
+// IEEE 754 binary16, round to nearest even
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x7f_ffff;
+
+    if exp == 0xff {
+        return sign | 0x7c00 | if mant != 0 { 0x200 } else { 0 };
+    }
+
+    let e = exp - 127 + 15;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    let round = |half: u32, rem: u32, halfway: u32| {
+        if rem > halfway || (rem == halfway && half & 1 == 1) {
+            half + 1
+        } else {
+            half
+        }
+    };
+
+    if e <= 0 {
+        if e < -10 {
+            return sign;
+        }
+        let mant = mant | 0x80_0000;
+        let shift = (14 - e) as u32;
+        let half = round(mant >> shift, mant & ((1 << shift) - 1), 1 << (shift - 1));
+        return sign | half as u16;
+    }
+
+    // A carry out of the mantissa correctly bumps the exponent
+    let half = round(((e as u32) << 10) | (mant >> 13), mant & 0x1fff, 0x1000);
+    sign | half as u16
+}
+
+pub fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half as u32) & 0x8000) << 16;
+    let exp = ((half >> 10) & 0x1f) as u32;
+    let mant = (half & 0x3ff) as u32;
+
+    match exp {
+        0 => {
+            let magnitude = mant as f32 * f32::powi(2.0, -24);
+            if sign != 0 { -magnitude } else { magnitude }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mant << 13)),
+        _ => f32::from_bits(sign | ((exp + 127 - 15) << 23) | (mant << 13)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::locomotion::{Locomotion, SIM_DT};
+    use crate::components::observation::PolicyMetadata;
+
+    const ARGS_JSON: &str = include_str!("../../data/policies/attn/args.json");
+    const WEIGHTS_JSON: &str = include_str!("../../data/policies/attn/weights.json");
+    const MESH_JSON: &str = include_str!("../../data/agents/biped/mesh.json");
+    const POLICY_JSON: &str = include_str!("../../data/agents/biped/policy.json");
+
+    // Observations along a rollout of the f32 policy in the soft-body sim
+    fn rollout(model: &AttentionModel, steps: usize) -> Vec<PolicyInput> {
+        let mut loco = Locomotion::new(MESH_JSON, POLICY_JSON).unwrap();
+        (0..steps)
+            .map(|_| {
+                let input = loco.observe();
+                loco.step(&model.forward_input(&input), SIM_DT);
+                input
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_f16_conversion() {
+        for &x in &[0.0f32, -0.0, 1.0, -2.5, 65504.0, 6.1035156e-5, 5.9604645e-8] {
+            assert_eq!(f16_to_f32(f32_to_f16(x)), x);
+        }
+        assert_eq!(f32_to_f16(1.0 + f32::powi(2.0, -11)), 0x3c00); // ties to even
+        assert!(f16_to_f32(f32_to_f16(1e6)).is_infinite());
+        assert!((f16_to_f32(f32_to_f16(0.1)) - 0.1).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_quantized_error_report() {
+        let model = AttentionModel::try_new(ARGS_JSON, WEIGHTS_JSON).unwrap();
+        let observations = rollout(&model, 150);
+        let f32_size = model.weights.values().map(|t| t.len() * 4).sum::<usize>();
+
+        // The policy's da is clamped to max_abs_da before it reaches a muscle,
+        // so that is the scale a wrong output is judged on. The requirement:
+        // a quantized policy misses the f32 step by at most 5% of the largest
+        // step, and by 1% on average, since activations carry each step's
+        // error into the next. Errors are on the raw da, which the clamp can
+        // only shrink.
+        let max_abs_da = PolicyMetadata::from_json(POLICY_JSON).unwrap().max_abs_da;
+        let (max_allowed, mean_allowed) = (0.05 * max_abs_da, 0.01 * max_abs_da);
+
+        let report = |mode| {
+            let quantized = QuantizedAttentionModel::from_model(&model, mode);
+            let report = compare(&model, &quantized, &observations);
+            assert!(quantized.size_bytes() < f32_size);
+            assert_eq!(report.samples, observations.len() * 19);
+            report
+        };
+
+        let f16 = report(Quantization::F16);
+        assert!(f16.max_abs_error < max_allowed && f16.mean_abs_error < mean_allowed, "{:?}", f16);
+        // Int8 is off by as much as a whole step somewhere along the rollout:
+        // not acceptable for this model, whichever way the scales are shared
+        for mode in [Quantization::Int8PerRow, Quantization::Int8PerTensor] {
+            let int8 = report(mode);
+            assert!(int8.max_abs_error > max_allowed, "{:?}: {:?}", mode, int8);
+        }
+    }
+}
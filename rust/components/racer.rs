@@ -0,0 +1,468 @@
+use crate::components::bot::Bot;
+use crate::components::controller::{Controller, MuscleMap, PolicySlot};
+use crate::components::cpg::CpgController;
+use crate::components::creature::{Creature, Morphology};
+use crate::components::input::{InputBinding, InputState};
+use crate::components::locomotion::{Locomotion, SIM_DT};
+use crate::components::rules::LapCounter;
+use crate::components::stamina::Stamina;
+use crate::components::terrain::Terrain;
+
+pub const MAX_RACERS: usize = 8;
+
+// Kinematic racing: muscles push the creature on against a drag its mass
+// resists, up to a top speed set by its stride. Tuned so the biped keeps the
+// old 15 m/s² and per-tick friction of 0.95 at 60 fps.
+pub const THRUST_GAIN: f32 = 0.46875; // m/s² per N/kg of muscle force
+pub const DRAG: f32 = 154.0;          // N per m/s
+pub const STRIDE_RATE: f32 = 4.0;     // strides per second, scaled by sqrt(limbs)
+pub const BRAKE_FACTOR: f32 = 2.0;
+pub const FINISH_LINE_X: f32 = 20.0;
+
+pub const START_X: f32 = -10.0;
+pub const LANE_WIDTH: f32 = 4.0;
+
+// Morphing: the creature has no thrust while it changes form, and must wait
+// before changing again
+pub const MORPH_TIME: f32 = 0.75;
+pub const MORPH_COOLDOWN: f32 = 2.0;
+
+// Steering drifts a racer across the road, at most a lane either way
+pub const STEER_SPEED: f32 = 4.0; // m/s at full lock
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MorphState {
+    pub target: Option<Morphology>, // changing into this for `remaining` seconds
+    pub remaining: f32,
+    pub cooldown: f32,
+    held: bool, // morph input on last tick; holding it does not repeat
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dynamics {
+    pub acceleration: f32, // m/s² at full effort
+    pub max_speed: f32,    // m/s
+    pub drag: f32,         // 1/s
+}
+
+impl Dynamics {
+    pub fn from_creature(creature: &Creature) -> Self {
+        let muscle_force = creature.max_force();
+        let legs = creature.limbs.len().max(1) as f32;
+        let stride = creature.limbs.iter().map(|limb| limb.length).sum::<f32>() / legs;
+        Self {
+            acceleration: THRUST_GAIN * muscle_force / creature.mass,
+            max_speed: STRIDE_RATE * stride * legs.sqrt(),
+            drag: DRAG / creature.mass,
+        }
+    }
+}
+
+// The soft body a racer runs in, in physics races. Distance is how far its
+// center of mass has moved forward since the start.
+pub struct RacerBody {
+    pub locomotion: Locomotion,
+    pub morphology: Morphology,
+    origin_x: f32,
+    sim_time: f32, // frame time not simulated yet
+}
+
+impl RacerBody {
+    pub fn new(locomotion: Locomotion, morphology: Morphology) -> Self {
+        let origin_x = locomotion.center_of_mass()[0];
+        Self { locomotion, morphology, origin_x, sim_time: 0.0 }
+    }
+
+    // Distance run and height of the center of mass
+    pub fn progress(&self) -> (f32, f32) {
+        let [x, y] = self.locomotion.center_of_mass();
+        (x - self.origin_x, y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RacerStats {
+    pub distance: f32, // net, going backwards takes it off again
+    pub top_speed: f32,
+    pub finish_time: Option<f64>, // seconds since the start
+    pub false_start: bool,
+    pub dnf: bool, // still out when the race was called
+}
+
+pub struct Racer {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub rotation_y: f32,
+    pub speed: f32,
+    pub reverse: f32, // fraction of top speed the brake reverses up to
+    pub penalty: f32, // seconds still held at the line for a false start
+    pub throttle: f32, // input this tick: up to 1 accelerating, down to -1 braking
+    pub lane: usize,
+    pub lateral: f32, // steered away from the lane's center, left positive
+    pub lane_width: f32, // the track's, set by GameMode::layout
+    pub binding: InputBinding,
+    pub bot: Option<Bot>, // drives the racer instead of its binding
+    pub creature: Creature,
+    pub dynamics: Dynamics,
+    pub morph: MorphState,
+    pub stamina: Stamina,
+    pub terrain: Terrain, // under the racer, set by RaceRules
+    pub policy: PolicySlot,
+    pub body: Option<RacerBody>,
+    pub stats: RacerStats,
+    pub laps: LapCounter,
+}
+
+impl Racer {
+    // Lanes are spread around z = 0, lane 0 on top: two racers get the
+    // original z = 2 / z = -2 lanes.
+    pub fn new(lane: usize, num_lanes: usize, morphology: Morphology) -> Self {
+        let creature = Creature::new(morphology);
+        Self {
+            x: START_X,
+            y: 1.0,
+            z: Self::lane_z(lane, num_lanes, LANE_WIDTH),
+            rotation_y: 0.0,
+            speed: 0.0,
+            reverse: 0.0,
+            penalty: 0.0,
+            throttle: 0.0,
+            lane,
+            lateral: 0.0,
+            lane_width: LANE_WIDTH,
+            binding: InputBinding::default_for(lane),
+            bot: None,
+            dynamics: Dynamics::from_creature(&creature),
+            creature,
+            morph: MorphState::default(),
+            stamina: Stamina::default(),
+            terrain: Terrain::Flat,
+            policy: PolicySlot::default(),
+            body: None,
+            stats: RacerStats::default(),
+            laps: LapCounter::default(),
+        }
+    }
+
+    pub fn lane_z(lane: usize, num_lanes: usize, lane_width: f32) -> f32 {
+        (num_lanes as f32 - 1.0) / 2.0 * lane_width - lane as f32 * lane_width
+    }
+
+    // Where the racer is across the track, left of the centerline positive
+    pub fn lane_offset(&self, num_lanes: usize) -> f32 {
+        Self::lane_z(self.lane, num_lanes, self.lane_width) + self.lateral
+    }
+
+    // Share of the muscles' full force asked for this tick, 0 to 1. A policy
+    // or CPG, if one drives the creature, modulates each muscle; without one
+    // they all pull fully. None while morphing.
+    pub fn activation(&self) -> f32 {
+        if self.morph.target.is_some() {
+            return 0.0;
+        }
+        if self.policy.output().is_empty() {
+            return 1.0;
+        }
+        let activated: f32 = self.creature.limbs.iter().flat_map(|limb| limb.muscles.iter()).map(|m| m.max_force * m.activation).sum();
+        (activated / self.creature.max_force()).clamp(0.0, 1.0)
+    }
+
+    // Force the muscles can put out this tick, as far as stamina allows
+    pub fn muscle_force(&self) -> f32 {
+        self.activation() * self.creature.max_force() * self.stamina.force_cap()
+    }
+
+    pub fn can_morph(&self) -> bool {
+        self.morph.target.is_none() && self.morph.cooldown <= 0.0
+    }
+
+    // Starts changing into `morphology`; false while morphing, cooling down
+    // or already in that form
+    pub fn start_morph(&mut self, morphology: Morphology) -> bool {
+        if !self.can_morph() || morphology == self.creature.morphology {
+            return false;
+        }
+        self.morph.target = Some(morphology);
+        self.morph.remaining = MORPH_TIME;
+        true
+    }
+
+    // The new body takes over. A controller made for the old one cannot
+    // drive it, so a driven racer falls back to the new form's CPG gait
+    // until a matching policy is loaded. In physics races the game then
+    // swaps in the new form's body.
+    fn finish_morph(&mut self, morphology: Morphology) {
+        self.creature = Creature::new(morphology);
+        self.dynamics = Dynamics::from_creature(&self.creature);
+        // The old body's muscles no longer map onto anything
+        self.policy.meta = None;
+        self.policy.muscles = None;
+        if self.policy.controller.is_some() {
+            self.policy.swap(Controller::Cpg(CpgController::for_morphology(morphology)));
+        }
+        self.morph = MorphState { cooldown: MORPH_COOLDOWN, held: self.morph.held, ..MorphState::default() };
+    }
+
+    fn update_morph(&mut self, delta: f32, input: &InputState) {
+        let pressed = input.morph;
+        if pressed && !self.morph.held {
+            let next = match self.creature.morphology {
+                Morphology::Biped => Morphology::Quadruped,
+                Morphology::Quadruped => Morphology::Hexapod,
+                Morphology::Hexapod => Morphology::Biped,
+            };
+            self.start_morph(next);
+        }
+        self.morph.held = pressed;
+
+        if let Some(target) = self.morph.target {
+            self.morph.remaining -= delta;
+            if self.morph.remaining <= 0.0 {
+                self.finish_morph(target);
+            }
+        } else {
+            self.morph.cooldown = (self.morph.cooldown - delta).max(0.0);
+        }
+    }
+
+    // The body takes over from kinematic driving. Its policy.json limits
+    // apply to the racer's policy, its muscles are mapped onto the form's
+    // limbs, and a racer with no controller gets its form's CPG gait so it
+    // moves at all.
+    pub fn attach_body(&mut self, body: RacerBody) {
+        self.policy.meta = Some(body.locomotion.meta);
+        self.policy.muscles = Some(MuscleMap::new(body.morphology, &body.locomotion.builder));
+        if self.policy.controller.is_none() {
+            self.policy.swap(Controller::Cpg(CpgController::for_morphology(body.morphology)));
+        }
+        self.body = Some(body);
+    }
+
+    pub fn detach_body(&mut self) {
+        self.body = None;
+        self.policy.meta = None;
+        self.policy.muscles = None;
+    }
+
+    // Muscles, form and speed from the policy and the racer's input, on the
+    // terrain under the racer. Movement is up to the game mode.
+    pub fn drive(&mut self, delta: f32, input: &InputState) {
+        let mut input = *input;
+        if self.penalty > 0.0 {
+            self.penalty = (self.penalty - delta).max(0.0);
+            input.throttle = 0.0;
+        }
+        if self.body.is_some() {
+            self.drive_body(delta, &input);
+            return;
+        }
+        self.update_morph(delta, &input);
+        self.policy.step(delta, None);
+        self.policy.drive(&mut self.creature);
+
+        // Accelerating wins over braking, as with keys alone
+        self.throttle = if input.throttle > 0.0 { input.throttle } else { -input.brake };
+
+        let terrain = self.terrain.modifiers(self.creature.morphology);
+        let thrust = if input.throttle > 0.0 {
+            input.throttle * THRUST_GAIN * self.muscle_force() / self.creature.mass
+        } else {
+            -input.brake * self.dynamics.acceleration * BRAKE_FACTOR
+        };
+        let effort = input.throttle * self.activation();
+        self.stamina.update(self.creature.morphology, effort, delta);
+        let max_speed = self.dynamics.max_speed * terrain.speed;
+        self.speed += (thrust * terrain.traction - self.dynamics.drag * self.speed) * delta;
+        self.speed = self.speed.clamp(-max_speed * self.reverse, max_speed);
+        self.stats.top_speed = self.stats.top_speed.max(self.speed);
+        // Steering right moves towards negative offsets
+        self.lateral = (self.lateral - input.steer * STEER_SPEED * delta).clamp(-self.lane_width, self.lane_width);
+    }
+
+    // Input sets the policy's intent instead of the speed: the throttle lets
+    // it run, without it the muscles relax to rest so the body coasts to a
+    // stop. The soft body is a 2D side view, so there is no turning; racers
+    // keep their lanes. The sim runs at its own fixed step.
+    fn drive_body(&mut self, delta: f32, input: &InputState) {
+        self.update_morph(delta, input);
+        let go = if self.morph.target.is_none() { input.throttle } else { 0.0 };
+        self.throttle = if go > 0.0 { go } else { -input.brake };
+
+        let Some(body) = self.body.as_mut() else {
+            return;
+        };
+        body.sim_time += delta;
+        while body.sim_time >= SIM_DT {
+            body.sim_time -= SIM_DT;
+            let input = body.locomotion.observe();
+            self.policy.step(SIM_DT, Some(&input));
+            // Contraction is 1 - activation; stamina limits how far
+            let effort = go * self.stamina.force_cap();
+            let activations: Vec<f32> = self
+                .policy
+                .muscle_output(body.locomotion.activations.len())
+                .iter()
+                .map(|&a| 1.0 - effort * (1.0 - a))
+                .collect();
+            let contraction = activations.iter().map(|&a| 1.0 - a).sum::<f32>() / activations.len().max(1) as f32;
+            self.stamina.update(self.creature.morphology, contraction, SIM_DT);
+            body.locomotion.drive(&activations, SIM_DT);
+        }
+
+        let (distance, _) = body.progress();
+        if delta > 0.0 {
+            self.speed = (distance - self.stats.distance) / delta;
+        }
+        self.stats.top_speed = self.stats.top_speed.max(self.speed);
+    }
+
+    pub fn advance(&mut self, delta: f32) {
+        self.x += self.speed * delta;
+        self.stats.distance += self.speed * delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL_THROTTLE: InputState = InputState { throttle: 1.0, brake: 0.0, steer: 0.0, morph: false };
+
+    fn keys(racer: &Racer, keys: &[&str]) -> InputState {
+        InputState::read(&racer.binding, |code| keys.contains(&code), &[])
+    }
+
+    #[test]
+    fn test_lanes() {
+        assert_eq!(Racer::lane_z(0, 2, LANE_WIDTH), 2.0);
+        assert_eq!(Racer::lane_z(1, 2, LANE_WIDTH), -2.0);
+        assert_eq!(Racer::lane_z(0, 1, LANE_WIDTH), 0.0);
+        let lanes: Vec<f32> = (0..MAX_RACERS).map(|lane| Racer::lane_z(lane, MAX_RACERS, LANE_WIDTH)).collect();
+        assert!(lanes.windows(2).all(|w| w[0] - w[1] == LANE_WIDTH));
+        assert_eq!(Racer::lane_z(0, 2, 1.5), 0.75);
+    }
+
+    #[test]
+    fn test_binding_drives_racer() {
+        let mut racer = Racer::new(1, 2, Morphology::Biped);
+        racer.drive(0.1, &keys(&racer, &["KeyW"]));
+        assert_eq!(racer.speed, 0.0);
+
+        racer.drive(0.1, &keys(&racer, &["ArrowUp"]));
+        racer.advance(0.1);
+        assert!(racer.speed > 0.0);
+        assert!(racer.x > START_X);
+        assert_eq!(racer.stats.top_speed, racer.speed);
+    }
+
+    #[test]
+    fn test_analog_input() {
+        let mut half = Racer::new(0, 1, Morphology::Biped);
+        let mut full = Racer::new(0, 1, Morphology::Biped);
+        let input = InputState { throttle: 0.5, steer: 1.0, ..FULL_THROTTLE };
+        for _ in 0..30 {
+            half.drive(1.0 / 60.0, &input);
+            full.drive(1.0 / 60.0, &FULL_THROTTLE);
+        }
+        assert!(half.speed > 0.0 && half.speed < full.speed);
+        assert_eq!(half.throttle, 0.5);
+        // Half a second at full lock to the right
+        assert!((half.lateral + STEER_SPEED * 0.5).abs() < 1e-4);
+        assert_eq!(half.lane_offset(1), half.lateral);
+        for _ in 0..120 {
+            half.drive(1.0 / 60.0, &input);
+        }
+        assert_eq!(half.lateral, -LANE_WIDTH);
+    }
+
+    #[test]
+    fn test_morphologies_differ() {
+        let run = |morphology, seconds: f32| {
+            let mut racer = Racer::new(0, 1, morphology);
+            for _ in 0..(seconds * 60.0) as usize {
+                racer.drive(1.0 / 60.0, &FULL_THROTTLE);
+            }
+            racer.speed
+        };
+        assert!((Dynamics::from_creature(&Creature::new(Morphology::Biped)).acceleration - 15.0).abs() < 1e-4);
+
+        // The heavy hexapod is slowest off the line but fastest flat out
+        let off_the_line: Vec<f32> = [Morphology::Biped, Morphology::Quadruped, Morphology::Hexapod].iter().map(|&m| run(m, 0.2)).collect();
+        let flat_out: Vec<f32> = [Morphology::Biped, Morphology::Quadruped, Morphology::Hexapod].iter().map(|&m| run(m, 5.0)).collect();
+        assert!(off_the_line[2] < off_the_line[0] && off_the_line[2] < off_the_line[1], "{:?}", off_the_line);
+        assert!(flat_out[0] < flat_out[1] && flat_out[1] < flat_out[2], "{:?}", flat_out);
+        assert_eq!(flat_out[2], Dynamics::from_creature(&Creature::new(Morphology::Hexapod)).max_speed);
+    }
+
+    #[test]
+    fn test_morph() {
+        let mut racer = Racer::new(0, 1, Morphology::Biped);
+        let tick = |racer: &mut Racer, pressed: &[&str]| {
+            let input = keys(racer, pressed);
+            racer.drive(1.0 / 60.0, &input);
+        };
+        tick(&mut racer, &["KeyE"]);
+        assert_eq!(racer.morph.target, Some(Morphology::Quadruped));
+
+        // No thrust while changing, holding the key does not queue another
+        for _ in 0..(MORPH_TIME * 60.0) as usize - 1 {
+            tick(&mut racer, &["KeyW", "KeyE"]);
+            assert_eq!(racer.speed, 0.0);
+        }
+        tick(&mut racer, &["KeyE"]);
+        assert_eq!(racer.creature.morphology, Morphology::Quadruped);
+        assert_eq!(racer.dynamics, Dynamics::from_creature(&Creature::new(Morphology::Quadruped)));
+
+        // Cooling down
+        tick(&mut racer, &[]);
+        tick(&mut racer, &["KeyE"]);
+        assert!(racer.morph.target.is_none());
+        assert!(!racer.start_morph(Morphology::Hexapod));
+        for _ in 0..(MORPH_COOLDOWN * 60.0) as usize {
+            tick(&mut racer, &[]);
+        }
+        assert!(!racer.start_morph(Morphology::Quadruped));
+        assert!(racer.start_morph(Morphology::Hexapod));
+    }
+
+    #[test]
+    fn test_terrain_favors_form() {
+        let flat_out = |morphology, terrain| {
+            let mut racer = Racer::new(0, 1, morphology);
+            racer.terrain = terrain;
+            // Up to speed, before stamina runs low
+            for _ in 0..180 {
+                racer.drive(1.0 / 60.0, &FULL_THROTTLE);
+            }
+            racer.speed
+        };
+        // On sand the hexapod walks away; on stairs the biped beats it
+        assert!(flat_out(Morphology::Hexapod, Terrain::Sand) > 2.0 * flat_out(Morphology::Biped, Terrain::Sand));
+        assert!(flat_out(Morphology::Biped, Terrain::Stairs) > flat_out(Morphology::Hexapod, Terrain::Stairs));
+    }
+
+    #[test]
+    fn test_stamina_paces_racer() {
+        let mut racer = Racer::new(0, 1, Morphology::Biped);
+        for _ in 0..60 {
+            racer.drive(1.0 / 60.0, &FULL_THROTTLE);
+        }
+        let fresh = racer.speed;
+        assert!(racer.stamina.level < 1.0);
+
+        // Flat out long enough, the muscles give out and the racer slows
+        for _ in 0..60 * 10 {
+            racer.drive(1.0 / 60.0, &FULL_THROTTLE);
+        }
+        assert!(racer.stamina.force_cap() < 1.0);
+        assert!(racer.speed < fresh);
+
+        // Coasting gets the breath back
+        let tired = racer.stamina.level;
+        for _ in 0..60 {
+            racer.drive(1.0 / 60.0, &InputState::default());
+        }
+        assert!(racer.stamina.level > tired);
+    }
+}
@@ -0,0 +1,39 @@
+// Small seeded generator (SplitMix64) so training runs and noise experiments
+// reproduce bit-for-bit from a seed, in wasm and natively alike.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+    spare_gaussian: Option<f32>,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed, spare_gaussian: None }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // Standard normal, Box-Muller
+    pub fn gaussian(&mut self) -> f32 {
+        if let Some(z) = self.spare_gaussian.take() {
+            return z;
+        }
+        let u1 = 1.0 - self.next_f32(); // (0, 1], keeps ln finite
+        let u2 = self.next_f32();
+        let r = (-2.0 * u1.ln()).sqrt();
+        let theta = 2.0 * std::f32::consts::PI * u2;
+        self.spare_gaussian = Some(r * theta.sin());
+        r * theta.cos()
+    }
+}
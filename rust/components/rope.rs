@@ -0,0 +1,232 @@
+// Tug-of-war rope. The two creatures are bodies in their own rapier world,
+// free to slide along x and joined by a prismatic joint whose limit is the
+// rope's length, so the rope goes slack when they close in and holds once it
+// is taut. Each creature pushes into the ground with its muscles, but never
+// harder than its grip allows, so heavy many-legged creatures out-pull light
+// strong ones. Whatever grip a creature does not pull with holds it in
+// place, as static friction against a motor-driven joint to the ground.
+//
+// The world is separate because tug-of-war racers have no physics bodies to
+// tie to: only physics races run soft bodies (GameState::set_body), and
+// everywhere else a racer is a point the mode moves along its lane. The
+// rope's world holds just these two points, each weighing its creature.
+
+use rapier2d::prelude::*;
+use crate::components::racer::Racer;
+
+pub const GROUND_FRICTION: f32 = 0.8; // same as the soft-body colliders
+pub const GROUND_DRAG: f32 = 2.0;     // 1/s, settles sliding creatures
+pub const GRAVITY: f32 = 9.81;
+const SUBSTEPS: usize = 8;
+const FOOTING_DAMPING: f32 = 1.0e6; // N s/m, stiff enough that held feet do not creep
+
+pub struct Rope {
+    pub rest_length: f32,
+    pub tension: f32, // N, from the joint's impulse
+    rigid_body_set: RigidBodySet,
+    collider_set: ColliderSet,
+    impulse_joint_set: ImpulseJointSet,
+    multibody_joint_set: MultibodyJointSet,
+    broad_phase: BroadPhase,
+    narrow_phase: NarrowPhase,
+    ccd_solver: CCDSolver,
+    integration_parameters: IntegrationParameters,
+    physics_pipeline: PhysicsPipeline,
+    island_manager: IslandManager,
+    ends: [RigidBodyHandle; 2],
+    joint: ImpulseJointHandle,
+    footing: [ImpulseJointHandle; 2], // each end to the ground
+}
+
+impl Rope {
+    // Tied between the first two racers where they stand, just taut
+    pub fn new(racers: &[Racer]) -> Self {
+        let rest_length = racers[1].x - racers[0].x;
+        let mut rigid_body_set = RigidBodySet::new();
+        let ends = [0, 1].map(|i| {
+            let racer = &racers[i];
+            rigid_body_set.insert(
+                RigidBodyBuilder::dynamic()
+                    .translation(vector![racer.x, 0.0])
+                    .additional_mass(racer.creature.mass)
+                    .locked_axes(LockedAxes::TRANSLATION_LOCKED_Y | LockedAxes::ROTATION_LOCKED)
+                    .linear_damping(GROUND_DRAG)
+                    .build(),
+            )
+        });
+
+        // Along x, the second end may come anywhere up to rest_length from the first
+        let rope = PrismaticJointBuilder::new(Vector::x_axis()).limits([0.0, rest_length]);
+        let mut impulse_joint_set = ImpulseJointSet::new();
+        let joint = impulse_joint_set.insert(ends[0], ends[1], rope, true);
+
+        // The motor holds each end still with up to its creature's spare grip
+        let ground = rigid_body_set.insert(RigidBodyBuilder::fixed().build());
+        let footing = ends.map(|end| {
+            let feet = PrismaticJointBuilder::new(Vector::x_axis()).motor_model(MotorModel::ForceBased).motor_velocity(0.0, FOOTING_DAMPING).motor_max_force(0.0);
+            impulse_joint_set.insert(ground, end, feet, true)
+        });
+
+        Self {
+            rest_length,
+            tension: 0.0,
+            rigid_body_set,
+            collider_set: ColliderSet::new(),
+            impulse_joint_set,
+            multibody_joint_set: MultibodyJointSet::new(),
+            broad_phase: BroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            ccd_solver: CCDSolver::new(),
+            integration_parameters: IntegrationParameters::default(),
+            physics_pipeline: PhysicsPipeline::new(),
+            island_manager: IslandManager::new(),
+            ends,
+            joint,
+            footing,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.tension = 0.0;
+        for &end in &self.ends {
+            self.rigid_body_set[end].set_linvel(vector![0.0, 0.0], true);
+        }
+    }
+
+    // The marker tied to the middle of the rope
+    pub fn marker(racers: &[Racer]) -> f32 {
+        (racers[0].x + racers[1].x) / 2.0
+    }
+
+    // Horizontal force a creature can put into the ground while its player
+    // pulls
+    pub fn traction(racer: &Racer) -> f32 {
+        let effort = racer.throttle.max(0.0);
+        let grip = GROUND_FRICTION * racer.creature.mass * GRAVITY;
+        (effort * racer.muscle_force()).min(grip)
+    }
+
+    // Static friction a creature's feet can still hold against being
+    // dragged: the part of its grip it is not pulling with, so a creature
+    // that does not pull stands its ground with all of it
+    pub fn hold(racer: &Racer) -> f32 {
+        GROUND_FRICTION * racer.creature.mass * GRAVITY - Self::traction(racer)
+    }
+
+    // Racer 0 pulls towards -x, racer 1 towards +x. The racers' positions
+    // and forms are the truth; the world carries their velocities.
+    pub fn step(&mut self, racers: &mut [Racer], delta: f32) {
+        let traction = [-Self::traction(&racers[0]), Self::traction(&racers[1])];
+        for (i, &end) in self.ends.iter().enumerate() {
+            let body = &mut self.rigid_body_set[end];
+            body.set_translation(vector![racers[i].x, 0.0], true);
+            body.set_additional_mass(racers[i].creature.mass, true);
+            body.reset_forces(true);
+            body.add_force(vector![traction[i], 0.0], true);
+            if let Some(feet) = self.impulse_joint_set.get_mut(self.footing[i]) {
+                feet.data.set_motor_max_force(JointAxis::X, Self::hold(&racers[i]));
+            }
+        }
+
+        self.integration_parameters.dt = delta / SUBSTEPS as f32;
+        for _ in 0..SUBSTEPS {
+            self.physics_pipeline.step(
+                &vector![0.0, 0.0],
+                &self.integration_parameters,
+                &mut self.island_manager,
+                &mut self.broad_phase,
+                &mut self.narrow_phase,
+                &mut self.rigid_body_set,
+                &mut self.collider_set,
+                &mut self.impulse_joint_set,
+                &mut self.multibody_joint_set,
+                &mut self.ccd_solver,
+                None,
+                &(),
+                &(),
+            );
+        }
+
+        // The limit's impulse covers the solver's last small step, a share of
+        // the last substep; over that time it is the force holding the rope
+        let impulse = self.impulse_joint_set.get(self.joint).and_then(|joint| joint.data.limits(JointAxis::X)).map_or(0.0, |limits| limits.impulse);
+        let small_step = self.integration_parameters.dt / self.integration_parameters.num_solver_iterations.get() as f32;
+        self.tension = impulse.abs() / small_step;
+        for (i, &end) in self.ends.iter().enumerate() {
+            racers[i].x = self.rigid_body_set[end].translation().x;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::creature::Morphology;
+    use crate::components::input::InputState;
+
+    fn pull(morphologies: [Morphology; 2], seconds: f32) -> (Vec<Racer>, Rope) {
+        pull_with(morphologies, [1.0, 1.0], seconds)
+    }
+
+    fn pull_with(morphologies: [Morphology; 2], throttles: [f32; 2], seconds: f32) -> (Vec<Racer>, Rope) {
+        let mut racers: Vec<Racer> = morphologies.iter().enumerate().map(|(lane, &m)| Racer::new(lane, 2, m)).collect();
+        racers[0].x = -5.0;
+        racers[1].x = 5.0;
+        let mut rope = Rope::new(&racers);
+        for _ in 0..(seconds * 60.0) as usize {
+            for (racer, &throttle) in racers.iter_mut().zip(&throttles) {
+                racer.drive(1.0 / 60.0, &InputState { throttle, ..InputState::default() });
+            }
+            rope.step(&mut racers, 1.0 / 60.0);
+        }
+        (racers, rope)
+    }
+
+    #[test]
+    fn test_equal_creatures_stall() {
+        let (racers, rope) = pull([Morphology::Quadruped; 2], 5.0);
+        assert!(Rope::marker(&racers).abs() < 1e-3);
+        // Both at full grip: the rope carries it
+        assert!((rope.tension - Rope::traction(&racers[0])).abs() < 1.0, "{}", rope.tension);
+    }
+
+    #[test]
+    fn test_grip_decides() {
+        // The biped's muscles are stronger than its grip; the hexapod's grip is larger
+        let (racers, rope) = pull([Morphology::Biped, Morphology::Hexapod], 3.0);
+        assert!(Rope::marker(&racers) > 1.0);
+        assert!(rope.tension > 0.0);
+        // The hexapod drags the biped along; the rope does not stretch
+        assert!(racers[1].x - racers[0].x <= rope.rest_length + 1e-3);
+    }
+
+    #[test]
+    fn test_idle_creature_stands_its_ground() {
+        // An equal creature pulling with all its grip cannot drag one that holds with all of it
+        let (racers, rope) = pull_with([Morphology::Biped; 2], [0.0, 1.0], 5.0);
+        assert_eq!(Rope::hold(&racers[0]), GROUND_FRICTION * racers[0].creature.mass * GRAVITY);
+        assert_eq!(Rope::hold(&racers[1]), 0.0);
+        assert!(Rope::marker(&racers).abs() < 1e-2, "{}", Rope::marker(&racers));
+        // Read off one solver step, so a little short with the footing in the solve too
+        assert!((rope.tension - Rope::traction(&racers[1])).abs() < 0.02 * Rope::traction(&racers[1]), "{}", rope.tension);
+
+        // A stronger grip drags it, against its full hold
+        let (racers, rope) = pull_with([Morphology::Biped, Morphology::Hexapod], [0.0, 1.0], 3.0);
+        assert!(Rope::marker(&racers) > 1.0);
+        assert!(rope.tension >= 0.98 * Rope::hold(&racers[0]), "{}", rope.tension);
+    }
+
+    #[test]
+    fn test_slack_rope_does_not_pull() {
+        let mut racers = vec![Racer::new(0, 2, Morphology::Biped), Racer::new(1, 2, Morphology::Biped)];
+        racers[0].x = -5.0;
+        racers[1].x = 5.0;
+        let mut rope = Rope::new(&racers);
+        racers[1].x = 3.0;
+        for _ in 0..60 {
+            rope.step(&mut racers, 1.0 / 60.0);
+        }
+        assert_eq!(rope.tension, 0.0);
+        assert_eq!((racers[0].x, racers[1].x), (-5.0, 3.0));
+    }
+}
@@ -1,44 +1,234 @@
+use crate::components::clock::TickClock;
+use crate::components::mode::GameMode;
 use crate::components::state::GameState;
 use std::f32::consts::PI;
 
+// Radius of the default lap track, a circle round the origin
+pub const LOOP_RADIUS: f32 = 10.0;
+pub const DEFAULT_TOTAL_LAPS: u32 = 3;
+
+// How far behind its best progress a racer may drop before it is flagged as
+// going the wrong way
+pub const WRONG_WAY_ANGLE: f32 = 0.25;
+
+// Angle-based lap counting: the racer's angle round the track is unwrapped
+// tick by tick, and every full turn in the racing direction is a lap. A lap
+// only counts if every checkpoint was passed on the way.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LapCounter {
+    pub lap: u32,
+    pub splits: Vec<f64>, // seconds taken for each completed lap
+    pub wrong_way: bool,
+    pub distance: f32,    // arc length along the centerline
+    pub checkpoint: usize, // next checkpoint to pass this lap
+    pub voided: u32,      // laps or finishes that skipped a checkpoint
+    last_position: [f32; 2],
+    last_driven: f32, // the racer's own distance, which runs on past the end
+    last_angle: f32,
+    total_angle: f32,
+    best_angle: f32,
+    lap_start: f64,
+    last_time: f64,
+}
+
+impl LapCounter {
+    pub fn start(angle: f32, now: f64) -> Self {
+        Self { last_angle: angle, lap_start: now, last_time: now, ..Self::default() }
+    }
+
+    // Laps driven so far, fractional
+    pub fn progress(&self) -> f32 {
+        self.total_angle / (PI * 2.0)
+    }
+
+    // Returns when the last lap counted this update was completed, found by
+    // interpolating between this update and the one before
+    pub fn update(&mut self, angle: f32, now: f64, num_checkpoints: usize) -> Option<f64> {
+        let mut angle_diff = angle - self.last_angle;
+
+        if angle_diff > PI {
+            angle_diff -= PI * 2.0;
+        }
+        if angle_diff < -PI {
+            angle_diff += PI * 2.0;
+        }
+
+        let last_total = self.total_angle;
+        let last_time = self.last_time;
+        self.total_angle += angle_diff;
+        self.last_angle = angle;
+        self.last_time = now;
+        self.best_angle = self.best_angle.max(self.total_angle);
+        self.wrong_way = self.total_angle < self.best_angle - WRONG_WAY_ANGLE;
+
+        // Backing over the line and crossing it again does not count twice.
+        // A voided lap's time carries over into the next split.
+        let lines_crossed = (self.total_angle / (PI * 2.0)).floor().max(0.0) as u32;
+        let mut completed = None;
+        while self.lap + self.voided < lines_crossed {
+            if self.checkpoint >= num_checkpoints {
+                let line = (self.lap + self.voided + 1) as f32 * PI * 2.0;
+                let time = crossing_time(last_total, self.total_angle, line, last_time, now);
+                self.splits.push(time - self.lap_start);
+                self.lap_start = time;
+                self.lap += 1;
+                completed = Some(time);
+            } else {
+                self.voided += 1;
+            }
+            self.checkpoint = 0;
+        }
+        completed
+    }
+}
+
+// When progress going from `from` at `then` to `to` at `now` passed `line`,
+// assuming steady speed in between
+fn crossing_time(from: f32, to: f32, line: f32, then: f64, now: f64) -> f64 {
+    let fraction = if to != from { ((line - from) / (to - from)).clamp(0.0, 1.0) } else { 1.0 };
+    then + (now - then) * fraction as f64
+}
+
 pub struct RaceRules;
 
 impl RaceRules {
-    pub fn start(state: &mut GameState, now: f64) {
-        state.race_started = true;
-        state.start_time = now;
-        state.last_angle = state.z.atan2(state.x);
-        state.total_angle = 0.0;
+    pub fn start(state: &mut GameState) {
+        let now = state.current_time;
+        let track = &state.track;
+        for racer in &mut state.racers {
+            let (s, _) = track.project([racer.x, racer.z]);
+            racer.laps = LapCounter::start(track.angle(s), now);
+            racer.laps.distance = s;
+            racer.laps.last_position = [racer.x, racer.z];
+            racer.laps.last_driven = racer.stats.distance;
+        }
     }
 
-    pub fn update(state: &mut GameState, now: f64) {
-        if !state.race_started || state.race_completed {
+    // Progress and checkpoints for every racer, then finishes: the last lap
+    // on closed tracks, the finish gate on open ones
+    pub fn update(state: &mut GameState) {
+        if state.mode == GameMode::TugOfWar {
             return;
         }
+        let now = state.current_time;
+        let then = (now - TickClock::seconds(1)).max(0.0);
+        let total_laps = state.total_laps;
+        let track = &state.track;
 
-        state.current_time = (now - state.start_time) / 1000.0;
+        for racer in &mut state.racers {
+            let position = [racer.x, racer.z];
+            let laps = &mut racer.laps;
+            let (s, _) = track.project(position);
+            laps.distance = s;
+            racer.terrain = track.terrain_at(s);
 
-        // Lap counting
-        let current_angle = state.z.atan2(state.x);
-        let mut angle_diff = current_angle - state.last_angle;
+            if let Some(&gate) = track.checkpoints.get(laps.checkpoint) {
+                if track.crosses(gate, laps.last_position, position) {
+                    laps.checkpoint += 1;
+                }
+            }
 
-        if angle_diff > PI {
-            angle_diff -= PI * 2.0;
+            if track.closed {
+                let completed = laps.update(track.angle(s), now, track.checkpoints.len());
+                if laps.lap >= total_laps && racer.stats.finish_time.is_none() {
+                    racer.stats.finish_time = Some(completed.unwrap_or(now));
+                }
+            } else if racer.stats.finish_time.is_none() && track.crosses(track.finish, laps.last_position, position) {
+                if laps.checkpoint >= track.checkpoints.len() {
+                    racer.stats.finish_time = Some(crossing_time(laps.last_driven, racer.stats.distance, track.finish, then, now));
+                } else {
+                    laps.voided += 1;
+                }
+            }
+            laps.last_position = position;
+            laps.last_driven = racer.stats.distance;
         }
-        if angle_diff < -PI {
-            angle_diff += PI * 2.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::creature::Morphology;
+    use crate::components::track::Track;
+
+    const OVAL_JSON: &str = include_str!("../../data/tracks/oval.json");
+
+    #[test]
+    fn test_lap_counter() {
+        let mut laps = LapCounter::start(0.0, 0.0);
+        // Two and a bit turns in steps that cross the ±PI seam
+        for i in 1..=45 {
+            let angle = (i as f32 * 0.3 + PI).rem_euclid(2.0 * PI) - PI;
+            laps.update(angle, i as f64, 0);
+        }
+        assert_eq!(laps.lap, 2);
+        assert_eq!(laps.splits.len(), 2);
+        assert!(!laps.wrong_way);
+
+        // Turning back
+        let mut angle = laps.last_angle;
+        for i in 0..3 {
+            angle -= 0.2;
+            laps.update(angle, 50.0 + i as f64, 0);
+        }
+        assert!(laps.wrong_way);
+        assert_eq!(laps.lap, 2);
+    }
+
+    #[test]
+    fn test_lap_race() {
+        let mut state = GameState::with_mode(GameMode::Laps, &[Morphology::Biped; 2]).unwrap();
+        state.total_laps = 2;
+        state.set_countdown(0.0);
+        state.start_game(0.0);
+        for tick in 0..60 * 60 {
+            state.update(1.0 / 60.0, (tick + 1) as f64 * 1000.0 / 60.0, r#"["ArrowUp"]"#);
         }
+        assert!(state.game_completed);
+        assert_eq!(state.winner, 2);
+        assert!(state.get_racer_dnf(0));
 
-        state.total_angle += angle_diff;
-        state.last_angle = current_angle;
+        let racer = state.racer(1).unwrap();
+        assert_eq!(racer.laps.splits.len(), 2);
+        let total: f64 = racer.laps.splits.iter().sum();
+        assert!((total - racer.stats.finish_time.unwrap()).abs() < 1e-9);
+        assert_eq!(state.racer(0).unwrap().laps.lap, 0);
+    }
 
-        let laps_completed = (state.total_angle.abs() / (PI * 2.0)).floor() as i32;
-        if laps_completed > state.lap {
-            state.lap = laps_completed;
+    #[test]
+    fn test_skipped_checkpoint_voids_lap() {
+        let mut state = GameState::new(GameMode::Laps, Morphology::Biped);
+        state.set_track(Track::from_json(OVAL_JSON).unwrap()).unwrap();
+        assert!(GameState::new(GameMode::Race, Morphology::Biped).set_track(Track::from_json(OVAL_JSON).unwrap()).is_err());
+        state.total_laps = 1;
+        state.set_countdown(0.0);
+        state.start_game(0.0);
+
+        let mut tick = 0;
+        let mut step = |state: &mut GameState| {
+            state.update(1.0 / 60.0, (tick + 1) as f64 * 1000.0 / 60.0, r#"["KeyW"]"#);
+            tick += 1;
+        };
+        while state.racer(0).unwrap().laps.checkpoint < 1 {
+            step(&mut state);
+        }
+        // Cut across the infield, past the other two checkpoints
+        let past_last = state.track().checkpoints[2] + 5.0;
+        state.racer_mut(0).unwrap().stats.distance = past_last;
+        while state.racer(0).unwrap().laps.voided == 0 {
+            step(&mut state);
         }
+        assert_eq!(state.racer(0).unwrap().laps.lap, 0);
+        assert!(!state.game_completed);
 
-        if state.lap >= state.total_laps {
-            state.race_completed = true;
+        // The next, full lap counts, timed from the start; racer 2 never
+        // sets off, so time is called on them
+        for _ in 0..90 * 60 {
+            step(&mut state);
         }
+        let racer = state.racer(0).unwrap();
+        assert!(state.game_completed);
+        assert_eq!(racer.laps.splits, vec![racer.stats.finish_time.unwrap()]);
     }
 }
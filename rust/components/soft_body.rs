@@ -13,9 +13,30 @@ pub struct SoftBodySimulation {
     pub physics_pipeline: PhysicsPipeline,
     pub island_manager: IslandManager,
     pub node_handles: Vec<RigidBodyHandle>,
-    pub muscle_joint_handles: Vec<ImpulseJointHandle>,
+    pub edges: Vec<[usize; 2]>,
+    pub edge_rest_lengths: Vec<f32>,
+    pub muscles: Vec<[usize; 2]>,
+    pub muscle_rest_lengths: Vec<f32>,
 }
 
+// Triangle edges hold the body's shape and muscles pull their endpoints
+// toward activation * l0. Both are damped springs applied as forces: rapier's
+// spring joints couple linear axes, which its 2D solver indexes out of bounds.
+const EDGE_STIFFNESS: f32 = 1000.0;
+const EDGE_DAMPING: f32 = 10.0;
+const MUSCLE_STIFFNESS: f32 = 1000.0;
+const MUSCLE_DAMPING: f32 = 10.0;
+
+// Every node weighs the same, as in algovivo
+const NODE_MASS: f32 = 1.0;
+
+// Explicit springs this stiff need smaller steps than SIM_DT to stay stable
+const SUBSTEPS: usize = 8;
+
+// Nodes collide with the ground but not with each other
+const NODE_GROUP: Group = Group::GROUP_1;
+const GROUND_GROUP: Group = Group::GROUP_2;
+
 impl SoftBodySimulation {
     pub fn new(mesh_json: &str) -> Self {
         let data: Value = serde_json::from_str(mesh_json).unwrap();
@@ -24,10 +45,7 @@ impl SoftBodySimulation {
 
         let mut rigid_body_set = RigidBodySet::new();
         let mut collider_set = ColliderSet::new();
-        let mut impulse_joint_set = ImpulseJointSet::new();
-        let multibody_joint_set = MultibodyJointSet::new();
         let mut node_handles = Vec::new();
-        let mut muscle_joint_handles = Vec::new();
 
         // 1. Create nodes as small rigid bodies
         for pos in nodes {
@@ -42,8 +60,10 @@ impl SoftBodySimulation {
             let handle = rigid_body_set.insert(rb);
             
             let collider = ColliderBuilder::ball(0.05)
+                .mass(NODE_MASS)
                 .friction(0.8)
                 .restitution(0.2)
+                .collision_groups(InteractionGroups::new(NODE_GROUP, GROUND_GROUP))
                 .build();
             collider_set.insert_with_parent(collider, handle, &mut rigid_body_set);
             
@@ -53,11 +73,18 @@ impl SoftBodySimulation {
         // 2. Create ground
         let ground_rb = RigidBodyBuilder::fixed().translation(vector![0.0, -1.0]).build();
         let ground_handle = rigid_body_set.insert(ground_rb);
-        let ground_collider = ColliderBuilder::cuboid(100.0, 1.0).build();
+        let ground_collider = ColliderBuilder::cuboid(100.0, 1.0)
+            .collision_groups(InteractionGroups::new(GROUND_GROUP, NODE_GROUP))
+            .build();
         collider_set.insert_with_parent(ground_collider, ground_handle, &mut rigid_body_set);
 
-        // 3. Create muscles (spring joints) between nodes based on triangles
-        let mut edges = std::collections::HashSet::new();
+        // 3. Springs along every triangle edge, at their rest lengths
+        let distance = |a: usize, b: usize| {
+            let (pa, pb) = (&nodes[a], &nodes[b]);
+            ((pa[0].as_f64().unwrap() - pb[0].as_f64().unwrap()).powi(2) +
+             (pa[1].as_f64().unwrap() - pb[1].as_f64().unwrap()).powi(2)).sqrt() as f32
+        };
+        let mut edges: Vec<[usize; 2]> = Vec::new();
         for tri in triangles {
             let tri_indices = tri.as_array().unwrap();
             let idxs = [
@@ -68,32 +95,30 @@ impl SoftBodySimulation {
 
             let pairs = [(idxs[0], idxs[1]), (idxs[1], idxs[2]), (idxs[2], idxs[0])];
             for (a, b) in pairs {
-                let key = if a < b { (a, b) } else { (b, a) };
+                let key = if a < b { [a, b] } else { [b, a] };
                 if !edges.contains(&key) {
-                    edges.insert(key);
-                    
-                    let pos_a = nodes[a].as_array().unwrap();
-                    let pos_b = nodes[b].as_array().unwrap();
-                    let dist = ((pos_a[0].as_f64().unwrap() - pos_b[0].as_f64().unwrap()).powi(2) + 
-                               (pos_a[1].as_f64().unwrap() - pos_b[1].as_f64().unwrap()).powi(2)).sqrt() as f32;
-
-                    // Use a spring-like joint
-                    let joint = SpringJointBuilder::new(dist, 1000.0, 10.0)
-                        .local_anchor1(point![0.0, 0.0])
-                        .local_anchor2(point![0.0, 0.0])
-                        .build();
-                    
-                    let handle = impulse_joint_set.insert(node_handles[a], node_handles[b], joint, true);
-                    muscle_joint_handles.push(handle);
+                    edges.push(key);
                 }
             }
         }
+        let edge_rest_lengths = edges.iter().map(|&[a, b]| distance(a, b)).collect();
+
+        // 4. Actuated muscles from the mesh, with rest lengths l0
+        let muscles: Vec<[usize; 2]> = data["muscles"]
+            .as_array()
+            .map(|ms| ms.iter().map(|m| [m[0].as_u64().unwrap() as usize, m[1].as_u64().unwrap() as usize]).collect())
+            .unwrap_or_default();
+        let muscle_rest_lengths = muscles
+            .iter()
+            .enumerate()
+            .map(|(i, &[a, b])| data["l0"][i].as_f64().map_or_else(|| distance(a, b), |l0| l0 as f32))
+            .collect();
 
         Self {
             rigid_body_set,
             collider_set,
-            impulse_joint_set,
-            multibody_joint_set,
+            impulse_joint_set: ImpulseJointSet::new(),
+            multibody_joint_set: MultibodyJointSet::new(),
             broad_phase: BroadPhase::new(),
             narrow_phase: NarrowPhase::new(),
             ccd_solver: CCDSolver::new(),
@@ -101,40 +126,84 @@ impl SoftBodySimulation {
             physics_pipeline: PhysicsPipeline::new(),
             island_manager: IslandManager::new(),
             node_handles,
-            muscle_joint_handles,
+            edges,
+            edge_rest_lengths,
+            muscles,
+            muscle_rest_lengths,
         }
     }
 
     pub fn step(&mut self, dt: f32, muscle_activations: &[f32]) {
-        self.integration_parameters.dt = dt;
+        self.integration_parameters.dt = dt / SUBSTEPS as f32;
         let gravity = vector![0.0, -9.81];
 
-        // Apply muscle activations by changing spring rest length
-        for (i, &activation) in muscle_activations.iter().enumerate() {
-            if i < self.muscle_joint_handles.len() {
-                let handle = self.muscle_joint_handles[i];
-                if let Some(joint) = self.impulse_joint_set.get_mut(handle) {
-                    // Joint data is a private field, so we use specific builder or accessors
-                    // For now, we skip detailed modification until the basic structure is stable
-                }
+        for _ in 0..SUBSTEPS {
+            // Forces persist across steps in rapier, so they are rebuilt each time
+            for &h in &self.node_handles {
+                self.rigid_body_set[h].reset_forces(false);
+            }
+            for i in 0..self.edges.len() {
+                self.add_spring(self.edges[i], self.edge_rest_lengths[i], EDGE_STIFFNESS, EDGE_DAMPING);
+            }
+            for i in 0..self.muscles.len() {
+                let activation = muscle_activations.get(i).copied().unwrap_or(1.0);
+                self.add_spring(self.muscles[i], activation * self.muscle_rest_lengths[i], MUSCLE_STIFFNESS, MUSCLE_DAMPING);
             }
+
+            self.physics_pipeline.step(
+                &gravity,
+                &self.integration_parameters,
+                &mut self.island_manager,
+                &mut self.broad_phase,
+                &mut self.narrow_phase,
+                &mut self.rigid_body_set,
+                &mut self.collider_set,
+                &mut self.impulse_joint_set,
+                &mut self.multibody_joint_set,
+                &mut self.ccd_solver,
+                None,
+                &(),
+                &(),
+            );
+        }
+    }
+
+    // Pulls nodes a and b toward `rest_length` apart
+    fn add_spring(&mut self, [a, b]: [usize; 2], rest_length: f32, stiffness: f32, damping: f32) {
+        let (ha, hb) = (self.node_handles[a], self.node_handles[b]);
+        let d = self.rigid_body_set[hb].translation() - self.rigid_body_set[ha].translation();
+        let len = d.norm();
+        if len < 1e-6 {
+            return;
         }
-        
-        self.physics_pipeline.step(
-            &gravity,
-            &self.integration_parameters,
-            &mut self.island_manager,
-            &mut self.broad_phase,
-            &mut self.narrow_phase,
-            &mut self.rigid_body_set,
-            &mut self.collider_set,
-            &mut self.impulse_joint_set,
-            &mut self.multibody_joint_set,
-            &mut self.ccd_solver,
-            None,
-            &mut (),
-            &mut (),
-        );
+        let dir = d / len;
+        let rel_vel = (self.rigid_body_set[hb].linvel() - self.rigid_body_set[ha].linvel()).dot(&dir);
+        let force = dir * (stiffness * (len - rest_length) + damping * rel_vel);
+        self.rigid_body_set[ha].add_force(force, true);
+        self.rigid_body_set[hb].add_force(-force, true);
+    }
+
+    pub fn num_muscles(&self) -> usize {
+        self.muscles.len()
+    }
+
+    pub fn set_node_state(&mut self, pos: &[[f32; 2]], vel: &[[f32; 2]]) {
+        for (i, &h) in self.node_handles.iter().enumerate() {
+            let rb = &mut self.rigid_body_set[h];
+            if let Some(p) = pos.get(i) {
+                rb.set_translation(vector![p[0], p[1]], true);
+            }
+            if let Some(v) = vel.get(i) {
+                rb.set_linvel(vector![v[0], v[1]], true);
+            }
+        }
+    }
+
+    pub fn get_node_velocities(&self) -> Vec<[f32; 2]> {
+        self.node_handles.iter().map(|&h| {
+            let rb = &self.rigid_body_set[h];
+            [rb.linvel().x, rb.linvel().y]
+        }).collect()
     }
 
     pub fn get_node_positions(&self) -> Vec<[f32; 2]> {
@@ -144,3 +213,28 @@ impl SoftBodySimulation {
         }).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESH_JSON: &str = include_str!("../../data/agents/biped/mesh.json");
+
+    #[test]
+    fn test_biped_holds_its_shape() {
+        let mut sim = SoftBodySimulation::new(MESH_JSON);
+        let rest = vec![1.0; sim.num_muscles()];
+        let squeeze = vec![0.7; sim.num_muscles()];
+        for step in 0..300 {
+            sim.step(0.033, if (step / 15) % 2 == 0 { &rest } else { &squeeze });
+        }
+        let pos = sim.get_node_positions();
+        assert!(pos.iter().flatten().all(|v| v.is_finite()));
+        // Standing on the ground, edges near their rest lengths
+        assert!(pos.iter().all(|p| p[1] > -0.1), "{:?}", pos);
+        for (&[a, b], &l0) in sim.edges.iter().zip(&sim.edge_rest_lengths) {
+            let len = ((pos[a][0] - pos[b][0]).powi(2) + (pos[a][1] - pos[b][1]).powi(2)).sqrt();
+            assert!((len - l0).abs() < 0.5 * l0, "edge {}-{}: {} vs {}", a, b, len, l0);
+        }
+    }
+}
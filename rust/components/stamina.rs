@@ -0,0 +1,93 @@
+use crate::components::creature::Morphology;
+
+// Effort drains stamina, easing off refills it, and a tired racer's muscles
+// cannot put out their full force. Pool sizes are fractions, 1 is fresh.
+pub const FULL_STAMINA: f32 = 1.0;
+
+// Below this the force cap falls off, down to EXHAUSTED_FORCE when empty
+pub const TIRED_BELOW: f32 = 0.3;
+pub const EXHAUSTED_FORCE: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StaminaRates {
+    pub drain: f32,    // per second at full effort
+    pub recovery: f32, // per second coasting
+}
+
+impl StaminaRates {
+    // Bipeds sprint but tire first; six legs share the load and can run
+    // flat out for longest, but take longest to get their breath back
+    pub fn for_morphology(morphology: Morphology) -> Self {
+        let (drain, recovery) = match morphology {
+            Morphology::Biped => (0.12, 0.25),
+            Morphology::Quadruped => (0.09, 0.18),
+            Morphology::Hexapod => (0.06, 0.12),
+        };
+        Self { drain, recovery }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stamina {
+    pub level: f32,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self { level: FULL_STAMINA }
+    }
+}
+
+impl Stamina {
+    // `effort` is the muscles' activation as a share of their full force,
+    // 0 to 1. Partial effort both drains and recovers, so some steady pace
+    // costs nothing.
+    pub fn update(&mut self, morphology: Morphology, effort: f32, delta: f32) {
+        let rates = StaminaRates::for_morphology(morphology);
+        let effort = effort.clamp(0.0, 1.0);
+        let change = rates.recovery * (1.0 - effort) - rates.drain * effort;
+        self.level = (self.level + change * delta).clamp(0.0, FULL_STAMINA);
+    }
+
+    // Share of the muscles' full force available
+    pub fn force_cap(&self) -> f32 {
+        if self.level >= TIRED_BELOW {
+            1.0
+        } else {
+            EXHAUSTED_FORCE + (1.0 - EXHAUSTED_FORCE) * self.level / TIRED_BELOW
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Seconds at full effort until the force cap starts to bite
+    fn flat_out(morphology: Morphology) -> f32 {
+        let mut stamina = Stamina::default();
+        let mut time = 0.0;
+        while stamina.force_cap() == 1.0 {
+            stamina.update(morphology, 1.0, 0.1);
+            time += 0.1;
+        }
+        time
+    }
+
+    #[test]
+    fn test_drain_and_recover() {
+        let mut stamina = Stamina::default();
+        for _ in 0..200 {
+            stamina.update(Morphology::Biped, 1.0, 0.1);
+        }
+        assert_eq!(stamina.level, 0.0);
+        assert_eq!(stamina.force_cap(), EXHAUSTED_FORCE);
+
+        stamina.update(Morphology::Biped, 0.0, 1.0);
+        assert!((stamina.level - 0.25).abs() < 1e-6);
+        assert!(stamina.force_cap() > EXHAUSTED_FORCE && stamina.force_cap() < 1.0);
+
+        assert!(flat_out(Morphology::Biped) < flat_out(Morphology::Quadruped));
+        assert!(flat_out(Morphology::Quadruped) < flat_out(Morphology::Hexapod));
+    }
+}
@@ -1,66 +1,485 @@
 use wasm_bindgen::prelude::*;
-use crate::components::creature::{Creature, Morphology};
-use crate::components::policy::AttentionModel;
-use crate::components::soft_body::SoftBodySimulation;
+use crate::components::clock::TickClock;
+use crate::components::creature::Morphology;
+use crate::components::mode::GameMode;
+use crate::components::phase::Phases;
+use crate::components::locomotion::Locomotion;
+use crate::components::racer::{Racer, RacerBody};
+use crate::components::rope::Rope;
+use crate::components::rules::DEFAULT_TOTAL_LAPS;
+use crate::components::track::Track;
 
 #[wasm_bindgen]
 pub struct GameState {
-    // Creature 1 (top lane, facing right)
-    pub creature1_x: f32,
-    pub creature1_y: f32,
-    pub creature1_z: f32,
-    pub creature1_rotation_y: f32,
-    pub creature1_speed: f32,
-    
-    // Creature 2 (bottom lane, facing right) 
-    pub creature2_x: f32,
-    pub creature2_y: f32,
-    pub creature2_z: f32,
-    pub creature2_rotation_y: f32,
-    pub creature2_speed: f32,
-    
+    pub mode: GameMode,
+
     // Racing game state
     pub game_started: bool,
     pub game_completed: bool,
-    pub start_time: f64,
-    pub current_time: f64,
-    pub winner: i32,  // 0 = no winner, 1 = creature1 wins, 2 = creature2 wins
-    
-    #[allow(dead_code)]
-    pub(crate) creature1: Creature,
-    pub(crate) creature2: Creature,
-    pub(crate) policy: Option<AttentionModel>,
-    pub(crate) sim: Option<SoftBodySimulation>,
+    pub start_time: f64,   // host timestamp, in ms
+    pub current_time: f64, // seconds, counted in ticks
+    pub winner: i32,  // 0 = no winner, otherwise racer index + 1
+    pub total_laps: u32, // lap races only
+
+    pub(crate) clock: TickClock,
+    pub(crate) phases: Phases,
+    pub(crate) racers: Vec<Racer>, // one per lane, top to bottom
+    pub(crate) rope: Option<Rope>,  // tug-of-war only
+    pub(crate) track: Track,
+    pub(crate) bodies: Vec<BodySource>, // physics races only
+}
+
+// data/agents/<name>/mesh.json and policy.json for one morphology
+pub struct BodySource {
+    pub morphology: Morphology,
+    pub mesh_json: String,
+    pub policy_json: String,
 }
 
 impl GameState {
-    pub fn new(morphology: Morphology) -> Self {
-        Self {
-            // Creature 1: left side, facing right (0 degrees)
-            creature1_x: -5.0,
-            creature1_y: 1.0,
-            creature1_z: 0.0,
-            creature1_rotation_y: 0.0,
-            creature1_speed: 0.0,
-            
-            // Creature 2: right side, facing left (180 degrees)
-            creature2_x: 5.0,
-            creature2_y: 1.0,
-            creature2_z: 0.0,
-            creature2_rotation_y: std::f32::consts::PI,
-            creature2_speed: 0.0,
-            
-            // Tug-of-war game state
+    // The mode's usual number of racers, all of one morphology
+    pub fn new(mode: GameMode, morphology: Morphology) -> Self {
+        Self::with_mode(mode, &vec![morphology; mode.default_num_racers()]).unwrap()
+    }
+
+    // One racer per morphology, laid out for the mode
+    pub fn with_mode(mode: GameMode, morphologies: &[Morphology]) -> Result<Self, String> {
+        if !mode.supports(morphologies.len()) {
+            return Err(format!("{:?} does not support {} racers", mode, morphologies.len()));
+        }
+        let num_lanes = morphologies.len();
+        let mut racers: Vec<Racer> = morphologies.iter().enumerate().map(|(lane, &m)| Racer::new(lane, num_lanes, m)).collect();
+        let track = mode.default_track();
+        mode.layout(&mut racers, &track);
+
+        Ok(Self {
+            mode,
             game_started: false,
             game_completed: false,
             start_time: 0.0,
             current_time: 0.0,
             winner: 0,
-            
-            creature1: Creature::new(morphology),
-            creature2: Creature::new(morphology),
-            policy: None,
-            sim: None,
+            total_laps: DEFAULT_TOTAL_LAPS,
+
+            clock: TickClock::default(),
+            phases: Phases::default(),
+            rope: (mode == GameMode::TugOfWar).then(|| Rope::new(&racers)),
+            racers,
+            track,
+            bodies: Vec::new(),
+        })
+    }
+
+    pub fn racer(&self, index: usize) -> Option<&Racer> {
+        self.racers.get(index)
+    }
+
+    pub fn racer_mut(&mut self, index: usize) -> Option<&mut Racer> {
+        self.racers.get_mut(index)
+    }
+
+    pub fn racers(&self) -> &[Racer] {
+        &self.racers
+    }
+
+    pub fn track(&self) -> &Track {
+        &self.track
+    }
+
+    // Physics races: every racer of `morphology` runs in this soft body,
+    // including racers that morph into it later
+    pub fn set_body(&mut self, morphology: Morphology, mesh_json: &str, policy_json: &str) -> Result<(), String> {
+        if self.mode != GameMode::PhysicsRace {
+            return Err(format!("{:?} has no soft bodies", self.mode));
+        }
+        // Fail now rather than on the first tick
+        Locomotion::new(mesh_json, policy_json)?;
+        self.bodies.retain(|b| b.morphology != morphology);
+        self.bodies.push(BodySource { morphology, mesh_json: mesh_json.to_string(), policy_json: policy_json.to_string() });
+        for racer in self.racers.iter_mut().filter(|r| r.creature.morphology == morphology) {
+            racer.body = None;
+        }
+        self.sync_bodies();
+        Ok(())
+    }
+
+    // Gives each racer a fresh body for its current form if it has none or
+    // has just morphed out of the one it had
+    pub(crate) fn sync_bodies(&mut self) {
+        for racer in &mut self.racers {
+            let morphology = racer.creature.morphology;
+            if racer.body.as_ref().is_some_and(|b| b.morphology == morphology) {
+                continue;
+            }
+            racer.detach_body();
+            let Some(source) = self.bodies.iter().find(|b| b.morphology == morphology) else {
+                continue;
+            };
+            if let Ok(locomotion) = Locomotion::new(&source.mesh_json, &source.policy_json) {
+                racer.attach_body(RacerBody::new(locomotion, morphology));
+            }
+        }
+    }
+
+    // Racers go back to the start of the new track
+    pub fn set_track(&mut self, track: Track) -> Result<(), String> {
+        if !self.mode.accepts(&track) {
+            return Err(format!("{:?} cannot run on {} track {:?}", self.mode, if track.closed { "a closed" } else { "an open" }, track.name));
+        }
+        self.mode.layout(&mut self.racers, &track);
+        self.track = track;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::racer::MAX_RACERS;
+
+    const MESH_JSON: &str = include_str!("../../data/agents/biped/mesh.json");
+    const POLICY_JSON: &str = include_str!("../../data/agents/biped/policy.json");
+
+    fn run(state: &mut GameState, keys: &str, ticks: usize) {
+        state.set_countdown(0.0);
+        state.start_game(0.0);
+        run_on(state, keys, ticks);
+    }
+
+    // More ticks of a race already under way
+    fn run_on(state: &mut GameState, keys: &str, ticks: usize) {
+        let start = state.get_current_tick() as usize;
+        for tick in start..start + ticks {
+            state.update(1.0 / 60.0, (tick + 1) as f64 * 1000.0 / 60.0, keys);
+        }
+    }
+
+    #[test]
+    fn test_racer_count() {
+        assert!(GameState::with_mode(GameMode::Race, &[]).is_err());
+        assert!(GameState::with_mode(GameMode::Race, &[Morphology::Biped; MAX_RACERS + 1]).is_err());
+        assert!(GameState::with_mode(GameMode::TugOfWar, &[Morphology::Biped; 3]).is_err());
+        let state = GameState::with_mode(GameMode::Race, &[Morphology::Biped, Morphology::Hexapod, Morphology::Quadruped]).unwrap();
+        assert_eq!(state.racers().len(), 3);
+        assert_eq!(state.racer(1).unwrap().creature.morphology, Morphology::Hexapod);
+    }
+
+    #[test]
+    fn test_devices_through_bindings() {
+        use crate::components::input::GAMEPAD_STRIDE;
+
+        let mut state = GameState::new(GameMode::Race, Morphology::Biped);
+        state.set_countdown(0.0);
+        state.start_game(0.0);
+        let keys = state.get_bound_keys();
+        assert_eq!(&keys[..5], ["KeyW", "KeyS", "KeyA", "KeyD", "KeyE"]);
+
+        // Player 1 holds W and A, player 2's gamepad half-presses the trigger
+        let pressed: Vec<u8> = keys.iter().map(|k| (k == "KeyW" || k == "KeyA") as u8).collect();
+        let mut pads = vec![0.0; 2 * GAMEPAD_STRIDE];
+        pads[GAMEPAD_STRIDE + 7] = 0.5;
+        for tick in 0..30 {
+            state.update_devices(1.0 / 60.0, (tick + 1) as f64 * 1000.0 / 60.0, &pressed, &pads);
+        }
+        let (p1, p2) = (state.racer(0).unwrap(), state.racer(1).unwrap());
+        assert_eq!(p1.throttle, 1.0);
+        assert_eq!(p2.throttle, 0.5);
+        assert!(p1.speed > p2.speed && p2.speed > 0.0);
+        // Steered left, off the lane's center
+        assert!(p1.z > Racer::lane_z(0, 2, p1.lane_width));
+
+        assert!(state.load_bindings(r#"[{"throttle": "KeyX", "brake": "KeyZ"}]"#));
+        assert_eq!(state.racer(0).unwrap().binding.throttle, "KeyX");
+        assert_eq!(state.racer(1).unwrap().binding.throttle, "ArrowUp");
+    }
+
+    // Everything a race's outcome depends on, bit for bit
+    fn snapshot(state: &GameState) -> Vec<(u32, u32, u32, Morphology, Option<f64>)> {
+        state
+            .racers()
+            .iter()
+            .map(|r| (r.x.to_bits(), r.z.to_bits(), r.speed.to_bits(), r.creature.morphology, r.stats.finish_time))
+            .collect()
+    }
+
+    #[test]
+    fn test_same_inputs_same_race() {
+        use crate::components::input::InputState;
+
+        // A scripted input sequence: pulsing throttle, weaving, morphing
+        let script = |tick: u64| -> Vec<InputState> {
+            (0..3)
+                .map(|i| InputState {
+                    throttle: if (tick + i) % 7 < 5 { 1.0 } else { 0.3 },
+                    brake: if (tick + 2 * i).is_multiple_of(23) { 1.0 } else { 0.0 },
+                    steer: ((tick as f32 * 0.05 + i as f32).sin()).clamp(-1.0, 1.0),
+                    morph: (tick + 40 * i).is_multiple_of(90),
+                })
+                .collect()
+        };
+        let play = || {
+            let mut state = GameState::with_mode(GameMode::Race, &[Morphology::Biped, Morphology::Quadruped, Morphology::Hexapod]).unwrap();
+            state.set_countdown(0.0);
+            state.start_game(0.0);
+            for tick in 0..900 {
+                state.tick(&script(tick));
+            }
+            state
+        };
+        let (a, b) = (play(), play());
+        assert!(a.game_completed);
+        assert_eq!(a.get_current_tick(), b.get_current_tick());
+        assert_eq!(snapshot(&a), snapshot(&b));
+        assert_eq!(a.winner, b.winner);
+
+        // Steady and ragged frame timing land on the same ticks
+        let mut steady = GameState::new(GameMode::Race, Morphology::Biped);
+        let mut ragged = GameState::new(GameMode::Race, Morphology::Biped);
+        steady.set_countdown(0.0);
+        steady.start_game(500.0);
+        ragged.set_countdown(0.0);
+        ragged.start_game(500.0);
+        let keys = r#"["KeyW", "KeyA", "ArrowUp"]"#;
+        for tick in 1..=120 {
+            steady.update(1.0 / 60.0, 500.0 + tick as f64 * 1000.0 / 60.0, keys);
+        }
+        let mut now: f64 = 500.0;
+        while ragged.get_current_tick() < 120 {
+            now = (now + [7.0, 31.0, 12.5, 50.0][ragged.get_current_tick() as usize % 4]).min(2500.0);
+            ragged.update(0.0, now, keys);
+        }
+        assert_eq!(ragged.get_current_tick(), 120);
+        assert_eq!(snapshot(&steady), snapshot(&ragged));
+        assert_eq!(steady.current_time, 2.0);
+    }
+
+    #[test]
+    fn test_countdown_and_false_start() {
+        use crate::components::phase::{RacePhase, FALSE_START_PENALTY};
+
+        let mut state = GameState::with_mode(GameMode::Race, &[Morphology::Biped; 3]).unwrap();
+        assert_eq!(state.get_race_phase(), RacePhase::Lobby);
+        assert!(state.phases.set_countdown(-1.0).is_err());
+        assert!(state.set_countdown(1.0));
+        state.start_game(0.0);
+        assert_eq!(state.get_race_phase(), RacePhase::Countdown);
+        assert_eq!(state.get_countdown_remaining(), 1.0);
+
+        // Player 1 jumps the start, player 2 waits for the go
+        let mut now = 0.0;
+        let mut frame = |state: &mut GameState, keys: &str| {
+            now += 1000.0 / 60.0;
+            state.update(1.0 / 60.0, now, keys);
+        };
+        for _ in 0..30 {
+            frame(&mut state, r#"["KeyW"]"#);
+        }
+        assert!((state.get_countdown_remaining() - 0.5).abs() < 1e-6);
+        assert_eq!(state.racer(0).unwrap().stats.distance, 0.0);
+        assert_eq!(state.current_time, 0.0);
+        for _ in 0..30 {
+            frame(&mut state, "[]");
+        }
+        assert_eq!(state.get_race_phase(), RacePhase::Racing);
+        assert_eq!(state.get_countdown_remaining(), 0.0);
+        assert!(state.get_racer_false_start(0) && !state.get_racer_false_start(1));
+        assert_eq!(state.get_racer_penalty(0), FALSE_START_PENALTY);
+
+        // Held at the line while player 2 gets away
+        for _ in 0..30 {
+            frame(&mut state, r#"["KeyW", "ArrowUp"]"#);
+        }
+        assert_eq!(state.racer(0).unwrap().speed, 0.0);
+        assert!(state.racer(1).unwrap().speed > 0.0);
+        assert!((state.current_time - 0.5).abs() < 1e-9);
+
+        // Player 2 is over the line first; the race goes on for player 1
+        while state.racer(1).unwrap().stats.finish_time.is_none() {
+            frame(&mut state, r#"["KeyW", "ArrowUp"]"#);
+        }
+        assert_eq!(state.get_race_phase(), RacePhase::Racing);
+        assert_eq!(state.get_racer_phase(1), RacePhase::Finished);
+        assert_eq!(state.get_racer_phase(0), RacePhase::Racing);
+        assert!(!state.game_completed);
+
+        while !state.game_completed {
+            frame(&mut state, r#"["KeyW", "ArrowUp"]"#);
+        }
+        assert_eq!(state.winner, 2);
+        assert!(!state.get_racer_dnf(0));
+        assert_eq!(state.get_race_phase(), RacePhase::Results);
+        assert_eq!(state.get_racer_phase(0), RacePhase::Results);
+        assert!(state.racer(0).unwrap().speed > 0.0);
+    }
+
+    #[test]
+    fn test_photo_finish() {
+        // Three identical racers flat out together: a true dead heat, where
+        // the last lane used to win. The fourth never starts and is not placed.
+        let mut state = GameState::with_mode(GameMode::Race, &[Morphology::Biped; 4]).unwrap();
+        run(&mut state, r#"["KeyW", "ArrowUp", "KeyI"]"#, 600);
+        assert!(!state.game_completed);
+        assert_eq!(state.standings(), vec![(0, 1), (1, 1), (2, 1), (3, 4)]);
+        run_on(&mut state, r#"["KeyW", "ArrowUp", "KeyI"]"#, 60 * 15);
+        assert!(state.game_completed);
+        assert!(state.is_dead_heat());
+        assert_eq!(state.winner, 1);
+        assert_eq!(state.standings(), vec![(0, 1), (1, 1), (2, 1), (3, 0)]);
+        assert!(state.get_racer_dnf(3) && !state.get_racer_dnf(0));
+        assert_eq!(state.get_racer_place(3), 0);
+        assert_eq!(state.get_racer_place(7), 0);
+        assert_eq!(state.get_finishing_order(), vec![0, 1, 2, 3]);
+
+        // Crossing times fall inside the tick, not on its end
+        let mut state = GameState::with_mode(GameMode::Race, &[Morphology::Biped]).unwrap();
+        run(&mut state, r#"["KeyW"]"#, 600);
+        let time = state.racer(0).unwrap().stats.finish_time.unwrap();
+        assert!(time < state.current_time && time > state.current_time - 1.0 / 60.0, "{} {}", time, state.current_time);
+
+        // Five millimetres ahead is enough, though both cross in one tick
+        let mut state = GameState::with_mode(GameMode::Race, &[Morphology::Biped; 2]).unwrap();
+        state.set_countdown(0.0);
+        state.start_game(0.0);
+        state.racer_mut(0).unwrap().stats.distance += 0.005;
+        for tick in 0..600 {
+            state.update(1.0 / 60.0, (tick + 1) as f64 * 1000.0 / 60.0, r#"["KeyW", "ArrowUp"]"#);
         }
+        assert!(!state.is_dead_heat());
+        assert_eq!(state.winner, 1);
+        assert_eq!(state.get_finishing_order(), vec![0, 1]);
+        let (a, b) = (state.racer(0).unwrap().stats.finish_time.unwrap(), state.racer(1).unwrap().stats.finish_time.unwrap());
+        assert!(a < b && b - a < 1.0 / 60.0);
+    }
+
+    #[test]
+    fn test_single_player_against_bots() {
+        use crate::components::bot::Difficulty;
+
+        let mut state = GameState::with_mode(GameMode::Race, &[Morphology::Biped; 3]).unwrap();
+        assert!(state.set_racer_bot(1, Difficulty::Easy));
+        assert!(state.set_racer_bot(2, Difficulty::Hard));
+        assert!(!state.set_racer_bot(3, Difficulty::Hard));
+        assert_eq!(state.get_racer_bot(2), Some(Difficulty::Hard));
+        assert_eq!(state.get_racer_bot(0), None);
+
+        // The bots ignore their keys; the harder one wins
+        run(&mut state, r#"["ArrowUp", "KeyI"]"#, 60 * 30);
+        assert!(state.game_completed);
+        assert_eq!(state.winner, 3);
+        assert!(state.racer(1).unwrap().stats.distance > 1.0);
+        assert_eq!(state.racer(0).unwrap().stats.distance, 0.0);
+
+        assert!(state.clear_racer_bot(2));
+        assert!(!state.clear_racer_bot(2));
+    }
+
+    #[test]
+    fn test_matchup() {
+        assert_eq!(Morphology::from_u8(1), Some(Morphology::Quadruped));
+        assert_eq!(Morphology::from_u8(3), None);
+
+        let mut state = GameState::with_mode(GameMode::Race, &[Morphology::Biped, Morphology::Quadruped]).unwrap();
+        assert_eq!(state.get_morphology(0), Some(Morphology::Biped));
+        assert_eq!(state.get_morphology(1), Some(Morphology::Quadruped));
+        assert_eq!(state.get_morphology(2), None);
+
+        // Both flat out over 30 m: the quadruped's longer stride tells
+        run(&mut state, r#"["KeyW", "ArrowUp"]"#, 600);
+        assert_eq!(state.winner, 2);
+    }
+
+    #[test]
+    fn test_bound_racer_wins() {
+        let mut state = GameState::with_mode(GameMode::Race, &[Morphology::Biped; 4]).unwrap();
+        // Racer 2's default binding; time is called on the others
+        run(&mut state, r#"["KeyI"]"#, 60 * 30);
+        assert!(state.game_completed);
+        assert_eq!(state.winner, 3);
+        assert!(state.get_racer_dnf(0) && !state.get_racer_dnf(2));
+        assert_eq!(state.racer(0).unwrap().x, state.racer(3).unwrap().x);
+    }
+
+    #[test]
+    fn test_tug_of_war() {
+        let mut state = GameState::new(GameMode::TugOfWar, Morphology::Biped);
+        assert_eq!(state.racer(0).unwrap().x, -5.0);
+        assert_eq!(state.racer(1).unwrap().rotation_y, std::f32::consts::PI);
+
+        // Player 2 pulls, but player 1 digs in with the same grip
+        run(&mut state, r#"["ArrowUp"]"#, 600);
+        assert!(!state.game_completed);
+        assert!(Rope::marker(&state.racers).abs() < 0.1);
+
+        // A hexapod's grip drags a biped that never pulls, so the rope stays taut behind it
+        let mut state = GameState::with_mode(GameMode::TugOfWar, &[Morphology::Biped, Morphology::Hexapod]).unwrap();
+        run(&mut state, r#"["ArrowUp"]"#, 600);
+        assert!(state.game_completed);
+        assert_eq!(state.winner, 2);
+        assert!(state.rope.as_ref().unwrap().tension > 0.0);
+    }
+
+    #[test]
+    fn test_track_lane_width() {
+        let mut state = GameState::with_mode(GameMode::Race, &[Morphology::Biped; 3]).unwrap();
+        state.set_track(Track::from_json(r#"{"waypoints": [[0, 0], [40, 0]], "lane_width": 1.5}"#).unwrap()).unwrap();
+        let z: Vec<f32> = state.racers.iter().map(|r| r.z).collect();
+        assert_eq!(z, vec![1.5, 0.0, -1.5]);
+
+        // Steering stops at the narrower lane's edge, halfway into the next lane
+        run(&mut state, r#"["KeyW", "KeyA"]"#, 60);
+        let p1 = state.racer(0).unwrap();
+        assert_eq!(p1.lateral, 1.5);
+        assert_eq!(p1.z, 3.0);
+    }
+
+    #[test]
+    fn test_time_trial_waits_for_everyone() {
+        let mut state = GameState::with_mode(GameMode::TimeTrial, &[Morphology::Biped; 2]).unwrap();
+        run(&mut state, r#"["KeyW"]"#, 600);
+        assert!(!state.game_completed);
+        assert!(state.racer(0).unwrap().stats.finish_time.is_some());
+
+        let mut state = GameState::new(GameMode::TimeTrial, Morphology::Quadruped);
+        run(&mut state, r#"["KeyW"]"#, 600);
+        assert!(state.game_completed);
+        assert_eq!(state.winner, 1);
+    }
+
+    #[test]
+    fn test_physics_race_follows_body() {
+        assert!(GameState::new(GameMode::Race, Morphology::Biped).set_body(Morphology::Biped, MESH_JSON, POLICY_JSON).is_err());
+
+        let mut state = GameState::with_mode(GameMode::PhysicsRace, &[Morphology::Biped, Morphology::Quadruped]).unwrap();
+        state.set_body(Morphology::Biped, MESH_JSON, POLICY_JSON).unwrap();
+        assert!(state.racer(0).unwrap().body.is_some());
+        assert!(state.racer(1).unwrap().body.is_none());
+        assert_eq!(state.get_policy_name(0), "cpg");
+        // Each slot takes its own body's policy.json and muscle map
+        assert_eq!(state.racer(0).unwrap().policy.meta.map(|m| m.center_vertex_id), Some(27));
+        assert_eq!(state.racer(0).unwrap().policy.muscles.as_ref().map(|m| m.num_limbs), Some(2));
+        assert!(state.racer(1).unwrap().policy.meta.is_none());
+
+        run(&mut state, r#"["KeyW", "ArrowUp"]"#, 120);
+        let racer = state.racer(0).unwrap();
+        let (distance, height) = racer.body.as_ref().unwrap().progress();
+        assert!(distance.is_finite() && distance != 0.0, "{}", distance);
+        assert_eq!((racer.stats.distance, racer.y), (distance, height));
+        assert_eq!(racer.x, state.track().position(distance, 2.0).0);
+        // The bodiless quadruped drove as in Race
+        assert!(state.racer(1).unwrap().stats.distance > 1.0);
+
+        // Without the go key the muscles rest
+        state.update(1.0 / 60.0, 3000.0, r#"["KeyS"]"#);
+        state.update(1.0 / 60.0, 3100.0, r#"["KeyS"]"#);
+        assert!(state.racer(0).unwrap().body.as_ref().unwrap().locomotion.activations.iter().all(|&a| a == 1.0));
+
+        // A bot runs the body through the racer's policy without any keys
+        let mut state = GameState::with_mode(GameMode::PhysicsRace, &[Morphology::Biped]).unwrap();
+        state.set_body(Morphology::Biped, MESH_JSON, POLICY_JSON).unwrap();
+        state.set_racer_bot(0, crate::components::bot::Difficulty::Medium);
+        run(&mut state, "[]", 120);
+        let body = state.racer(0).unwrap().body.as_ref().unwrap();
+        assert!(body.locomotion.activations.iter().any(|&a| a != 1.0));
+        assert!(body.progress().0 != 0.0);
     }
 }
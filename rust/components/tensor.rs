@@ -0,0 +1,169 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+// Row-major f32 tensor, as produced by `tensor.tolist()` on the Python side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensor {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+impl Tensor {
+    pub fn new(shape: Vec<usize>, data: Vec<f32>) -> Self {
+        debug_assert_eq!(shape.iter().product::<usize>(), data.len());
+        Self { shape, data }
+    }
+
+    pub fn zeros(shape: Vec<usize>) -> Self {
+        let len = shape.iter().product();
+        Self { shape, data: vec![0.0; len] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // Accepts nested lists (weights.json) as well as already flattened ones.
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let mut shape = Vec::new();
+        let mut data = Vec::new();
+
+        let mut level = value;
+        while let Value::Array(items) = level {
+            shape.push(items.len());
+            match items.first() {
+                Some(first) => level = first,
+                None => break,
+            }
+        }
+
+        Self::flatten(value, &shape, &mut data)?;
+        Ok(Self { shape, data })
+    }
+
+    // Nested lists formatted exactly like Python's json.dump(tensor.tolist())
+    pub fn write_python_json(&self, out: &mut String) {
+        fn nest(shape: &[usize], data: &[f32], out: &mut String) {
+            match shape.split_first() {
+                None => out.push_str(&python_float_repr(data[0] as f64)),
+                Some((&len, rest)) => {
+                    let stride = rest.iter().product::<usize>();
+                    out.push('[');
+                    for i in 0..len {
+                        if i > 0 {
+                            out.push_str(", ");
+                        }
+                        nest(rest, &data[i * stride..(i + 1) * stride], out);
+                    }
+                    out.push(']');
+                }
+            }
+        }
+        nest(&self.shape, &self.data, out);
+    }
+
+    fn flatten(value: &Value, shape: &[usize], out: &mut Vec<f32>) -> Result<(), String> {
+        match (value, shape.split_first()) {
+            (Value::Number(n), None) => {
+                out.push(n.as_f64().ok_or("non-finite weight")? as f32);
+                Ok(())
+            }
+            (Value::Array(items), Some((&len, rest))) if items.len() == len => {
+                items.iter().try_for_each(|item| Self::flatten(item, rest, out))
+            }
+            _ => Err(format!("ragged or non-numeric tensor, expected shape {:?}", shape)),
+        }
+    }
+}
+
+// repr() of a Python float: shortest round-trip digits, positional notation
+// for exponents in [-4, 16) and scientific with a two-digit exponent otherwise.
+pub fn python_float_repr(x: f64) -> String {
+    if x.is_nan() {
+        return "NaN".to_string();
+    }
+    if x.is_infinite() {
+        return if x > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    let sign = if x.is_sign_negative() { "-" } else { "" };
+    if x == 0.0 {
+        return format!("{}0.0", sign);
+    }
+
+    // {:e} finds the shortest round-trip length but breaks ties upward; redo
+    // it at that precision to get Python's correctly rounded (half-even) digits
+    let shortest = format!("{:e}", x.abs());
+    let precision = shortest.split_once('e').unwrap().0.replace('.', "").len() - 1;
+    let sci = format!("{:.*e}", precision, x.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    let digits = mantissa.replace('.', "");
+
+    if (-4..16).contains(&exp) {
+        if exp < 0 {
+            format!("{}0.{}{}", sign, "0".repeat((-exp - 1) as usize), digits)
+        } else {
+            let int_len = exp as usize + 1;
+            if digits.len() <= int_len {
+                format!("{}{}{}.0", sign, digits, "0".repeat(int_len - digits.len()))
+            } else {
+                format!("{}{}.{}", sign, &digits[..int_len], &digits[int_len..])
+            }
+        }
+    } else {
+        let mantissa = if digits.len() > 1 { format!("{}.{}", &digits[..1], &digits[1..]) } else { digits };
+        format!("{}{}e{}{:02}", sign, mantissa, if exp < 0 { '-' } else { '+' }, exp.abs())
+    }
+}
+
+pub fn tensors_from_json(json: &str) -> Result<HashMap<String, Tensor>, String> {
+    let map: HashMap<String, Value> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    map.iter()
+        .map(|(name, value)| Ok((name.clone(), Tensor::from_json(value).map_err(|e| format!("{}: {}", name, e))?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_and_flat() {
+        let nested = Tensor::from_json(&serde_json::json!([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]])).unwrap();
+        assert_eq!(nested.shape, vec![2, 3]);
+        assert_eq!(nested.data, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let flat = Tensor::from_json(&serde_json::json!([0.5, -0.5])).unwrap();
+        assert_eq!(flat.shape, vec![2]);
+
+        assert!(Tensor::from_json(&serde_json::json!([[1.0], [2.0, 3.0]])).is_err());
+
+        let mut json = String::new();
+        nested.write_python_json(&mut json);
+        assert_eq!(json, "[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]");
+    }
+
+    #[test]
+    fn test_python_float_repr() {
+        let cases = [
+            (1e-5, "1e-05"),
+            (1.5e-5, "1.5e-05"),
+            (1e16, "1e+16"),
+            (123456789012345678.0, "1.2345678901234568e+17"),
+            (0.0001, "0.0001"),
+            (1.0, "1.0"),
+            (-0.0, "-0.0"),
+            (100.0, "100.0"),
+            (1e15, "1000000000000000.0"),
+            (0.43444007635116577, "0.43444007635116577"),
+            (1.2980117797851562, "1.2980117797851562"),
+        ];
+        for (x, expected) in cases {
+            assert_eq!(python_float_repr(x), expected);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+use crate::components::creature::Morphology;
+
+// Track surfaces. Each favors one morphology, so switching form at the
+// right moment is what wins races.
+#[wasm_bindgen]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Terrain {
+    #[default]
+    Flat = 0,
+    Stairs = 1, // bipeds step up, many legs trip
+    Rocks = 2,  // quadrupeds scramble over
+    Sand = 3,   // six feet spread the load
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainModifiers {
+    pub speed: f32,    // scales top speed
+    pub traction: f32, // scales thrust and braking
+}
+
+impl Terrain {
+    pub fn modifiers(self, morphology: Morphology) -> TerrainModifiers {
+        let (speed, traction) = match (self, morphology) {
+            (Terrain::Flat, _) => (1.0, 1.0),
+            (Terrain::Stairs, Morphology::Biped) => (1.0, 1.0),
+            (Terrain::Stairs, Morphology::Quadruped) => (0.75, 0.9),
+            (Terrain::Stairs, Morphology::Hexapod) => (0.6, 0.8),
+            (Terrain::Rocks, Morphology::Biped) => (0.8, 0.9),
+            (Terrain::Rocks, Morphology::Quadruped) => (1.0, 1.0),
+            (Terrain::Rocks, Morphology::Hexapod) => (0.7, 0.8),
+            (Terrain::Sand, Morphology::Biped) => (0.6, 0.7),
+            (Terrain::Sand, Morphology::Quadruped) => (0.8, 0.85),
+            (Terrain::Sand, Morphology::Hexapod) => (1.0, 1.0),
+        };
+        TerrainModifiers { speed, traction }
+    }
+
+    // The form that runs this surface best
+    pub fn favors(self) -> Option<Morphology> {
+        match self {
+            Terrain::Flat => None,
+            Terrain::Stairs => Some(Morphology::Biped),
+            Terrain::Rocks => Some(Morphology::Quadruped),
+            Terrain::Sand => Some(Morphology::Hexapod),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_terrain_has_one_best_form() {
+        let forms = [Morphology::Biped, Morphology::Quadruped, Morphology::Hexapod];
+        for terrain in [Terrain::Stairs, Terrain::Rocks, Terrain::Sand] {
+            let best = terrain.favors().unwrap();
+            let top = terrain.modifiers(best);
+            assert_eq!(top, TerrainModifiers { speed: 1.0, traction: 1.0 });
+            for &m in forms.iter().filter(|&&m| m != best) {
+                let other = terrain.modifiers(m);
+                assert!(other.speed < top.speed && other.traction < top.traction, "{:?} {:?}", terrain, m);
+            }
+        }
+    }
+}
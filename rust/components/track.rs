@@ -0,0 +1,338 @@
+// Track definitions: a centerline through waypoints, lanes either side of it,
+// ordered checkpoint gates and a finish gate. Progress along the track is the
+// arc length of a position projected onto the centerline.
+
+use serde::Deserialize;
+use crate::components::racer::{FINISH_LINE_X, MAX_RACERS, START_X, LANE_WIDTH};
+use crate::components::terrain::Terrain;
+use std::f32::consts::PI;
+
+// Centerline points per waypoint segment on smoothed tracks
+const SAMPLES_PER_SEGMENT: usize = 8;
+
+// data/tracks/<name>.json. Checkpoints, the finish and terrain segments are
+// waypoint indices; the finish defaults to the last waypoint on open tracks
+// and to the first on closed ones, where it is also the start line.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TrackData {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default)]
+    pub smooth: bool, // Catmull-Rom through the waypoints
+    pub waypoints: Vec<[f32; 2]>, // x, z
+    #[serde(default = "default_lane_width")]
+    pub lane_width: f32,
+    #[serde(default)]
+    pub checkpoints: Vec<usize>,
+    pub finish: Option<usize>,
+    #[serde(default)]
+    pub terrain: Vec<TerrainSegment>,
+}
+
+// Between two waypoints, wrapping past the end on closed tracks
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct TerrainSegment {
+    pub kind: Terrain,
+    pub from: usize,
+    pub to: usize,
+}
+
+fn default_lane_width() -> f32 {
+    LANE_WIDTH
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub name: String,
+    pub closed: bool,
+    pub lane_width: f32,
+    pub checkpoints: Vec<f32>, // arc length of each gate, in the order they must be passed
+    pub finish: f32,
+    pub terrain: Vec<(f32, f32, Terrain)>, // arc length from, to; everywhere else is flat
+    points: Vec<[f32; 2]>, // closed tracks repeat the first point at the end
+    arc: Vec<f32>,         // arc length at each point
+}
+
+impl Track {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let data: TrackData = serde_json::from_str(json).map_err(|e| format!("track.json: {}", e))?;
+        Self::from_data(data)
+    }
+
+    pub fn from_data(data: TrackData) -> Result<Self, String> {
+        let n = data.waypoints.len();
+        if n < if data.closed { 3 } else { 2 } {
+            return Err(format!("track.json: {} waypoints is too few", n));
+        }
+        if data.lane_width <= 0.0 {
+            return Err("track.json: lane_width must be positive".to_string());
+        }
+        let finish = data.finish.unwrap_or(if data.closed { 0 } else { n - 1 });
+        let indices = data.checkpoints.iter().copied().chain(data.terrain.iter().flat_map(|t| [t.from, t.to]));
+        if finish >= n || indices.clone().any(|i| i >= n) {
+            return Err("track.json: waypoint index out of range".to_string());
+        }
+
+        let per_segment = if data.smooth { SAMPLES_PER_SEGMENT } else { 1 };
+        let points = centerline(&data.waypoints, data.closed, per_segment);
+        let mut arc = vec![0.0];
+        for w in points.windows(2) {
+            arc.push(arc[arc.len() - 1] + distance(w[0], w[1]));
+        }
+        if arc[arc.len() - 1] <= 0.0 {
+            return Err("track.json: track has no length".to_string());
+        }
+
+        let mut track = Self {
+            name: data.name,
+            closed: data.closed,
+            lane_width: data.lane_width,
+            checkpoints: Vec::new(),
+            finish: arc[finish * per_segment],
+            terrain: Vec::new(),
+            points,
+            arc,
+        };
+
+        // Gates in driving order: after the start on open tracks, after the
+        // finish line going round on closed ones
+        let mut last = if track.closed { 0.0 } else { track.arc[0] };
+        for &c in &data.checkpoints {
+            let s = track.arc[c * per_segment];
+            let along = if track.closed { (s - track.finish).rem_euclid(track.length()) } else { s };
+            if along <= last || (!track.closed && s >= track.finish) {
+                return Err(format!("track.json: checkpoint {} is out of order", c));
+            }
+            last = along;
+            track.checkpoints.push(s);
+        }
+
+        for segment in &data.terrain {
+            if segment.from == segment.to || (!track.closed && segment.from > segment.to) {
+                return Err(format!("track.json: terrain segment {}..{} is empty", segment.from, segment.to));
+            }
+            track.terrain.push((track.arc[segment.from * per_segment], track.arc[segment.to * per_segment], segment.kind));
+        }
+        Ok(track)
+    }
+
+    // The original arena: start at x = -10, finish line at x = 20
+    pub fn straight() -> Self {
+        Self::from_data(TrackData {
+            name: "straight".to_string(),
+            closed: false,
+            smooth: false,
+            waypoints: vec![[START_X, 0.0], [FINISH_LINE_X, 0.0]],
+            lane_width: LANE_WIDTH,
+            checkpoints: Vec::new(),
+            finish: None,
+            terrain: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    // Counter-clockwise seen from above, starting at (radius, 0)
+    pub fn circle(radius: f32) -> Self {
+        let waypoints = (0..64)
+            .map(|i| {
+                let theta = i as f32 / 64.0 * PI * 2.0;
+                [radius * theta.cos(), radius * theta.sin()]
+            })
+            .collect();
+        Self::from_data(TrackData {
+            name: "circle".to_string(),
+            closed: true,
+            smooth: false,
+            waypoints,
+            lane_width: LANE_WIDTH,
+            checkpoints: Vec::new(),
+            finish: None,
+            terrain: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    pub fn length(&self) -> f32 {
+        self.arc[self.arc.len() - 1]
+    }
+
+    pub fn centerline(&self) -> &[[f32; 2]] {
+        &self.points
+    }
+
+    // Point and unit tangent at arc length s. Closed tracks wrap; open ones
+    // carry on straight past either end.
+    pub fn sample(&self, s: f32) -> ([f32; 2], [f32; 2]) {
+        let s = if self.closed { s.rem_euclid(self.length()) } else { s };
+        let i = self.arc.partition_point(|&a| a <= s).clamp(1, self.arc.len() - 1) - 1;
+        let (a, b) = (self.points[i], self.points[i + 1]);
+        let len = self.arc[i + 1] - self.arc[i];
+        let t = [(b[0] - a[0]) / len, (b[1] - a[1]) / len];
+        let along = s - self.arc[i];
+        ([a[0] + t[0] * along, a[1] + t[1] * along], t)
+    }
+
+    // x, z and rotation_y for a racer `lateral` to the left of the centerline
+    pub fn position(&self, s: f32, lateral: f32) -> (f32, f32, f32) {
+        let (p, t) = self.sample(s);
+        let n = normal(t);
+        (p[0] + n[0] * lateral, p[1] + n[1] * lateral, -t[1].atan2(t[0]))
+    }
+
+    // Arc length of the nearest centerline point and the signed distance
+    // from it, positive to the left
+    pub fn project(&self, p: [f32; 2]) -> (f32, f32) {
+        let mut best = (f32::INFINITY, 0.0, 0.0);
+        for (i, w) in self.points.windows(2).enumerate() {
+            let (a, b) = (w[0], w[1]);
+            let len = self.arc[i + 1] - self.arc[i];
+            if len <= 0.0 {
+                continue;
+            }
+            let t = [(b[0] - a[0]) / len, (b[1] - a[1]) / len];
+            let d = [p[0] - a[0], p[1] - a[1]];
+            let along = (d[0] * t[0] + d[1] * t[1]).clamp(0.0, len);
+            let q = [a[0] + t[0] * along, a[1] + t[1] * along];
+            let dist = distance(p, q);
+            if dist < best.0 {
+                // Off the end of a segment the offset is not along its
+                // normal, but the side still is
+                let n = normal(t);
+                let side = (p[0] - q[0]) * n[0] + (p[1] - q[1]) * n[1];
+                best = (dist, self.arc[i] + along, dist.copysign(side));
+            }
+        }
+        (best.1, best.2)
+    }
+
+    // Whether moving from `from` to `to` passes forwards through the gate
+    // across the track at arc length s, wide enough for every lane
+    pub fn crosses(&self, s: f32, from: [f32; 2], to: [f32; 2]) -> bool {
+        let (c, t) = self.sample(s);
+        let before = (from[0] - c[0]) * t[0] + (from[1] - c[1]) * t[1];
+        let after = (to[0] - c[0]) * t[0] + (to[1] - c[1]) * t[1];
+        if !(before < 0.0 && after >= 0.0) {
+            return false;
+        }
+        let k = before / (before - after);
+        let x = [from[0] + (to[0] - from[0]) * k - c[0], from[1] + (to[1] - from[1]) * k - c[1]];
+        let n = normal(t);
+        (x[0] * n[0] + x[1] * n[1]).abs() <= self.lane_width * MAX_RACERS as f32 / 2.0
+    }
+
+    // The surface at arc length s; the first listed segment wins overlaps
+    pub fn terrain_at(&self, s: f32) -> Terrain {
+        let s = if self.closed { s.rem_euclid(self.length()) } else { s };
+        self.terrain
+            .iter()
+            .find(|&&(from, to, _)| if from < to { from <= s && s < to } else { s >= from || s < to })
+            .map_or(Terrain::Flat, |&(_, _, kind)| kind)
+    }
+
+    // Progress round a closed track as an angle, 0 on the finish line
+    pub fn angle(&self, s: f32) -> f32 {
+        (s - self.finish).rem_euclid(self.length()) / self.length() * PI * 2.0
+    }
+}
+
+// Left of the direction of travel; for a track along +x that is +z, where
+// lane 0 has always been
+fn normal(t: [f32; 2]) -> [f32; 2] {
+    [-t[1], t[0]]
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
+
+// Polyline through the waypoints; with more than one sample per segment it
+// follows a uniform Catmull-Rom spline, passing through every waypoint
+fn centerline(waypoints: &[[f32; 2]], closed: bool, per_segment: usize) -> Vec<[f32; 2]> {
+    let n = waypoints.len();
+    let get = |i: isize| -> [f32; 2] {
+        if closed {
+            waypoints[i.rem_euclid(n as isize) as usize]
+        } else {
+            waypoints[i.clamp(0, n as isize - 1) as usize]
+        }
+    };
+    let segments = if closed { n } else { n - 1 };
+
+    let mut points = Vec::with_capacity(segments * per_segment + 1);
+    for i in 0..segments as isize {
+        let (p0, p1, p2, p3) = (get(i - 1), get(i), get(i + 1), get(i + 2));
+        for k in 0..per_segment {
+            let u = k as f32 / per_segment as f32;
+            let (u2, u3) = (u * u, u * u * u);
+            let point = |j: usize| {
+                0.5 * (2.0 * p1[j]
+                    + (p2[j] - p0[j]) * u
+                    + (2.0 * p0[j] - 5.0 * p1[j] + 4.0 * p2[j] - p3[j]) * u2
+                    + (3.0 * p1[j] - p0[j] - 3.0 * p2[j] + p3[j]) * u3)
+            };
+            points.push([point(0), point(1)]);
+        }
+    }
+    points.push(if closed { waypoints[0] } else { waypoints[n - 1] });
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVAL_JSON: &str = include_str!("../../data/tracks/oval.json");
+
+    #[test]
+    fn test_straight_projection() {
+        let track = Track::straight();
+        assert_eq!(track.length(), 30.0);
+        assert_eq!(track.finish, 30.0);
+        assert_eq!(track.position(0.0, 2.0), (START_X, 2.0, 0.0));
+        let (s, lateral) = track.project([5.0, -2.0]);
+        assert!((s - 15.0).abs() < 1e-5 && (lateral + 2.0).abs() < 1e-5);
+        // Past the end the track carries on
+        assert_eq!(track.position(35.0, 0.0).0, 25.0);
+        assert!(track.crosses(track.finish, [19.9, 6.0], [20.1, 6.0]));
+        assert!(!track.crosses(track.finish, [20.1, 6.0], [19.9, 6.0]));
+        assert!(!track.crosses(track.finish, [19.9, 40.0], [20.1, 40.0]));
+    }
+
+    #[test]
+    fn test_oval() {
+        let track = Track::from_json(OVAL_JSON).unwrap();
+        assert!(track.closed);
+        assert_eq!(track.checkpoints.len(), 3);
+        assert_eq!(track.terrain_at(track.finish), Terrain::Flat);
+        assert_eq!(track.terrain_at(track.checkpoints[1]), Terrain::Rocks);
+        // Sand runs up to the end of the centerline, before the rocks
+        assert_eq!(track.terrain_at(track.length() - 1.0), Terrain::Sand);
+        assert_eq!(track.terrain_at(-1.0), Terrain::Sand);
+        assert_eq!(track.terrain_at(track.length() + 1.0), Terrain::Rocks);
+        // The spline passes through the waypoints
+        let (s, lateral) = track.project([30.0, 0.0]);
+        assert!(lateral.abs() < 1e-4);
+        assert!((s - track.checkpoints[1]).abs() < 1e-3);
+        // Positions project back to where they were placed
+        for i in 0..20 {
+            let s = track.length() * i as f32 / 20.0;
+            let (x, z, _) = track.position(s, -3.0);
+            let (ps, lateral) = track.project([x, z]);
+            assert!((ps - s).abs() < 0.05 || (ps - s).abs() > track.length() - 0.05, "{} {}", s, ps);
+            assert!((lateral + 3.0).abs() < 0.05, "{} {} {}", s, ps, lateral);
+        }
+    }
+
+    #[test]
+    fn test_invalid_tracks() {
+        assert!(Track::from_json(r#"{"waypoints": [[0, 0]]}"#).is_err());
+        assert!(Track::from_json(r#"{"waypoints": [[0, 0], [1, 0]], "finish": 2}"#).is_err());
+        assert!(Track::from_json(r#"{"waypoints": [[0, 0], [1, 0], [2, 0]], "checkpoints": [2]}"#).is_err());
+        assert!(Track::from_json(r#"{"waypoints": [[0, 0], [1, 0], [2, 0], [3, 0]], "checkpoints": [2, 1]}"#).is_err());
+        assert!(Track::from_json(r#"{"waypoints": [[0, 0], [1, 0], [2, 0], [3, 0]], "checkpoints": [1, 2]}"#).is_ok());
+        assert!(Track::from_json(r#"{"waypoints": [[0, 0], [1, 0]], "terrain": [{"kind": "sand", "from": 1, "to": 0}]}"#).is_err());
+        assert!(Track::from_json(r#"{"waypoints": [[0, 0], [1, 0]], "terrain": [{"kind": "lava", "from": 0, "to": 1}]}"#).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+// Golden trajectories written by generate_trajectory_with_attn_policy.py
+// (or trimmed from one by make_parity_fixture.py): mesh.json plus
+// steps/N.json, used to check the Rust policy and sim against Python.
+
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use crate::components::observation::{MeshData, ObservationBuilder, PolicyMetadata};
+use crate::components::policy::{AttentionModel, PolicyInput};
+use crate::components::soft_body::SoftBodySimulation;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TrajectoryStep {
+    pub pos0: Vec<[f32; 2]>,
+    pub vel0: Vec<[f32; 2]>,
+    pub a0: Vec<f32>,
+    pub policy_output: Vec<f32>,
+    // Absent in fixtures made before make_parity_fixture.py ran the simulator
+    pub pos1: Option<Vec<[f32; 2]>>,
+    pub vel1: Option<Vec<[f32; 2]>>,
+    pub a1: Option<Vec<f32>>,
+}
+
+pub struct Trajectory {
+    pub mesh_json: String,
+    pub mesh: MeshData,
+    pub steps: Vec<TrajectoryStep>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParityReport {
+    pub max_abs_error: f32,
+    pub worst_step: usize,
+    pub steps_checked: usize,
+}
+
+impl ParityReport {
+    fn new() -> Self {
+        Self { max_abs_error: 0.0, worst_step: 0, steps_checked: 0 }
+    }
+
+    fn record(&mut self, step: usize, expected: &[f32], actual: &[f32]) {
+        assert_eq!(expected.len(), actual.len(), "step {}: length mismatch", step);
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            let err = (e - a).abs();
+            if err > self.max_abs_error || err.is_nan() {
+                self.max_abs_error = err;
+                self.worst_step = step;
+            }
+        }
+        self.steps_checked += 1;
+    }
+}
+
+fn flatten(points: &[[f32; 2]]) -> Vec<f32> {
+    points.iter().flatten().copied().collect()
+}
+
+impl Trajectory {
+    pub fn load_dir(dirname: impl AsRef<Path>) -> Result<Self, String> {
+        let dirname = dirname.as_ref();
+        let read = |path: &Path| fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e));
+
+        let mesh_json = read(&dirname.join("mesh.json"))?;
+        let mesh = MeshData::from_json(&mesh_json)?;
+
+        // steps/0.json, steps/1.json, ... in numeric order
+        let mut steps = Vec::new();
+        loop {
+            let path = dirname.join("steps").join(format!("{}.json", steps.len()));
+            if !path.exists() {
+                break;
+            }
+            let step: TrajectoryStep = serde_json::from_str(&read(&path)?).map_err(|e| format!("{}: {}", path.display(), e))?;
+            steps.push(step);
+        }
+
+        if steps.is_empty() {
+            return Err(format!("{}: no steps", dirname.display()));
+        }
+        Ok(Self { mesh_json, mesh, steps })
+    }
+
+    pub fn observations(&self, builder: &ObservationBuilder) -> Vec<PolicyInput> {
+        self.steps.iter().map(|step| builder.build(&step.pos0, &step.vel0)).collect()
+    }
+
+    // Observation building + forward() against the recorded policy_output
+    pub fn policy_parity(&self, model: &AttentionModel, builder: &ObservationBuilder) -> ParityReport {
+        let mut report = ParityReport::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            let output = model.forward_input(&builder.build(&step.pos0, &step.vel0));
+            report.record(i, &step.policy_output, &output);
+        }
+        report
+    }
+
+    // Clamping of the recorded policy_output into the next activations
+    pub fn action_parity(&self, meta: &PolicyMetadata) -> ParityReport {
+        let mut report = ParityReport::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            if let Some(a1) = &step.a1 {
+                let mut a = step.a0.clone();
+                meta.apply(&mut a, &step.policy_output);
+                report.record(i, a1, &a);
+            }
+        }
+        report
+    }
+
+    // Restores each recorded state, steps the sim with the recorded
+    // activations and compares the result. None if the trajectory has no
+    // post-step states.
+    pub fn sim_parity(&self, sim: &mut SoftBodySimulation, dt: f32) -> Option<(ParityReport, ParityReport)> {
+        let mut pos_report = ParityReport::new();
+        let mut vel_report = ParityReport::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            let (pos1, vel1, a1) = (step.pos1.as_ref()?, step.vel1.as_ref()?, step.a1.as_ref()?);
+            sim.set_node_state(&step.pos0, &step.vel0);
+            sim.step(dt, a1);
+            pos_report.record(i, &flatten(pos1), &flatten(&sim.get_node_positions()));
+            vel_report.record(i, &flatten(vel1), &flatten(&sim.get_node_velocities()));
+        }
+        Some((pos_report, vel_report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/trajectories/biped_attn");
+    const ARGS_JSON: &str = include_str!("../../data/policies/attn/args.json");
+    const WEIGHTS_JSON: &str = include_str!("../../data/policies/attn/weights.json");
+    const POLICY_JSON: &str = include_str!("../../data/agents/biped/policy.json");
+
+    // algovivo's timestep
+    const SIM_DT: f32 = 0.033;
+    // The rapier spring mesh only approximates algovivo's FEM model, so one
+    // step from a recorded state lands near the recorded next state, not on it
+    const SIM_POS_TOLERANCE: f32 = 0.02;
+    const SIM_VEL_TOLERANCE: f32 = 0.5;
+
+    fn fixture() -> (Trajectory, ObservationBuilder, PolicyMetadata) {
+        let trajectory = Trajectory::load_dir(FIXTURE_DIR).unwrap();
+        let meta = PolicyMetadata::from_json(POLICY_JSON).unwrap();
+        let builder = ObservationBuilder::new(&trajectory.mesh, meta.center_vertex_id, meta.forward_vertex_id);
+        (trajectory, builder, meta)
+    }
+
+    #[test]
+    fn test_policy_parity() {
+        let (trajectory, builder, meta) = fixture();
+        let model = AttentionModel::try_new(ARGS_JSON, WEIGHTS_JSON).unwrap();
+
+        let report = trajectory.policy_parity(&model, &builder);
+        assert_eq!(report.steps_checked, trajectory.steps.len());
+        assert!(report.max_abs_error < 1e-4, "{:?}", report);
+
+        let report = trajectory.action_parity(&meta);
+        assert!(report.max_abs_error < 1e-5, "{:?}", report);
+    }
+
+    // The checked-in fixture predates the generator-only script; see its README.md
+    #[test]
+    #[ignore = "the fixture is not generator output and has no pos1/vel1; regenerate it with make_parity_fixture.py --agent"]
+    fn test_sim_parity() {
+        let (trajectory, _, _) = fixture();
+        let mut sim = SoftBodySimulation::new(&trajectory.mesh_json);
+        let (pos, vel) = trajectory.sim_parity(&mut sim, SIM_DT).expect("fixture has no pos1/vel1");
+        assert_eq!(pos.steps_checked, trajectory.steps.len());
+        assert!(pos.max_abs_error < SIM_POS_TOLERANCE, "{:?}", pos);
+        assert!(vel.max_abs_error < SIM_VEL_TOLERANCE, "{:?}", vel);
+    }
+}
@@ -0,0 +1,182 @@
+// Binary weights container, laid out like safetensors so standard tooling can
+// read it too:
+//
+//   u64 LE header length
+//   JSON header: {"<name>": {"dtype": "F32", "shape": [..], "data_offsets": [begin, end]}, ..}
+//                padded with spaces so the data section starts 8-byte aligned
+//   raw little-endian tensor data, offsets relative to the end of the header
+//
+// Reading borrows tensor data straight out of the input buffer; values are only
+// decoded when a model is built from them.
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use crate::components::tensor::Tensor;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dtype {
+    F32,
+}
+
+impl Dtype {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "F32" => Ok(Dtype::F32),
+            _ => Err(format!("unsupported dtype {}", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Dtype::F32 => "F32",
+        }
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            Dtype::F32 => 4,
+        }
+    }
+}
+
+pub struct TensorView<'a> {
+    pub name: String,
+    pub dtype: Dtype,
+    pub shape: Vec<usize>,
+    pub bytes: &'a [u8],
+}
+
+impl<'a> TensorView<'a> {
+    pub fn values(&self) -> impl Iterator<Item = f32> + 'a {
+        self.bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn to_tensor(&self) -> Tensor {
+        Tensor::new(self.shape.clone(), self.values().collect())
+    }
+}
+
+pub struct WeightsFile<'a> {
+    pub tensors: Vec<TensorView<'a>>, // in data order
+}
+
+impl<'a> WeightsFile<'a> {
+    pub fn get(&self, name: &str) -> Option<&TensorView<'a>> {
+        self.tensors.iter().find(|t| t.name == name)
+    }
+
+    pub fn to_tensors(&self) -> HashMap<String, Tensor> {
+        self.tensors.iter().map(|t| (t.name.clone(), t.to_tensor())).collect()
+    }
+}
+
+pub fn read(bytes: &[u8]) -> Result<WeightsFile<'_>, String> {
+    let len_bytes: [u8; 8] = bytes
+        .get(..8)
+        .and_then(|b| b.try_into().ok())
+        .ok_or("weights.bin: truncated header length")?;
+    let header_len = u64::from_le_bytes(len_bytes) as usize;
+    let header_end = 8usize.checked_add(header_len).filter(|&end| end <= bytes.len()).ok_or("weights.bin: truncated header")?;
+    let header: Map<String, Value> =
+        serde_json::from_slice(&bytes[8..header_end]).map_err(|e| format!("weights.bin header: {}", e))?;
+    let data = &bytes[header_end..];
+
+    let mut tensors = Vec::new();
+    for (name, info) in header.iter() {
+        if name == "__metadata__" {
+            continue;
+        }
+
+        let dtype = Dtype::parse(info["dtype"].as_str().unwrap_or(""))?;
+        let shape = info["shape"]
+            .as_array()
+            .and_then(|dims| dims.iter().map(|d| d.as_u64().map(|d| d as usize)).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| format!("weights.bin: {} has no shape", name))?;
+        let (begin, end) = match info["data_offsets"].as_array().map(|o| (o.first(), o.get(1))) {
+            Some((Some(b), Some(e))) => (
+                b.as_u64().unwrap_or(u64::MAX) as usize,
+                e.as_u64().unwrap_or(u64::MAX) as usize,
+            ),
+            _ => return Err(format!("weights.bin: {} has no data_offsets", name)),
+        };
+
+        if begin > end || end > data.len() {
+            return Err(format!("weights.bin: {} data out of bounds", name));
+        }
+        if end - begin != shape.iter().product::<usize>() * dtype.size() {
+            return Err(format!("weights.bin: {} size does not match shape {:?}", name, shape));
+        }
+
+        tensors.push(TensorView { name: name.clone(), dtype, shape, bytes: &data[begin..end] });
+    }
+
+    tensors.sort_by_key(|t| t.bytes.as_ptr() as usize);
+    Ok(WeightsFile { tensors })
+}
+
+pub fn write(tensors: &[(&str, &Tensor)]) -> Vec<u8> {
+    let mut header = Map::new();
+    let mut offset = 0;
+    for (name, tensor) in tensors {
+        let size = tensor.len() * Dtype::F32.size();
+        header.insert(
+            name.to_string(),
+            json!({
+                "dtype": Dtype::F32.name(),
+                "shape": tensor.shape,
+                "data_offsets": [offset, offset + size],
+            }),
+        );
+        offset += size;
+    }
+
+    let mut header_bytes = serde_json::to_vec(&header).unwrap();
+    header_bytes.resize(header_bytes.len().next_multiple_of(8), b' ');
+
+    let mut out = Vec::with_capacity(8 + header_bytes.len() + offset);
+    out.extend_from_slice(&(header_bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(&header_bytes);
+    for (_, tensor) in tensors {
+        for value in &tensor.data {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::policy::{AttentionModel, WEIGHT_NAMES};
+
+    const ARGS_JSON: &str = include_str!("../../data/policies/attn/args.json");
+    const WEIGHTS_JSON: &str = include_str!("../../data/policies/attn/weights.json");
+    const WEIGHTS_BIN: &[u8] = include_bytes!("../../data/policies/attn/weights.bin");
+
+    #[test]
+    fn test_round_trip() {
+        let a = Tensor::new(vec![2, 2], vec![1.0, -2.0, 3.5, f32::MIN_POSITIVE]);
+        let b = Tensor::new(vec![3], vec![0.0, 0.25, -0.0]);
+        let bytes = write(&[("a", &a), ("b", &b)]);
+
+        let file = read(&bytes).unwrap();
+        assert_eq!(file.tensors.len(), 2);
+        assert_eq!(file.get("a").unwrap().to_tensor(), a);
+        assert_eq!(file.get("b").unwrap().to_tensor(), b);
+        assert!(read(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_json_and_bin_load_identical_models() {
+        let from_json = AttentionModel::try_new(ARGS_JSON, WEIGHTS_JSON).unwrap();
+        let from_bin = AttentionModel::from_bin(ARGS_JSON, WEIGHTS_BIN).unwrap();
+
+        for name in WEIGHT_NAMES {
+            assert_eq!(from_json.weights[name], from_bin.weights[name], "{}", name);
+        }
+        let saved = AttentionModel::from_bin(ARGS_JSON, &from_json.weights_bin()).unwrap();
+        assert_eq!(saved.weights, from_json.weights);
+    }
+}
@@ -0,0 +1,147 @@
+// Robustness wrappers around the policy loop: Gaussian noise on the projected
+// observation, a fixed observation delay and a low-pass filter on da. All off
+// by default; the noise is seeded so experiments reproduce.
+
+use std::collections::VecDeque;
+use crate::components::policy::PolicyInput;
+use crate::components::rng::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WrapperConfig {
+    pub position_noise: f32, // std dev added to projected positions
+    pub velocity_noise: f32, // std dev added to projected velocities
+    pub latency_ticks: usize,
+    pub action_smoothing: f32, // 0 passes da through, towards 1 filters harder
+    pub seed: u64,
+}
+
+impl Default for WrapperConfig {
+    fn default() -> Self {
+        Self {
+            position_noise: 0.0,
+            velocity_noise: 0.0,
+            latency_ticks: 0,
+            action_smoothing: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PolicyWrappers {
+    config: WrapperConfig,
+    rng: Rng,
+    history: VecDeque<PolicyInput>,
+    smoothed_da: Vec<f32>,
+}
+
+impl Default for PolicyWrappers {
+    fn default() -> Self {
+        Self::new(WrapperConfig::default())
+    }
+}
+
+impl PolicyWrappers {
+    pub fn new(config: WrapperConfig) -> Self {
+        Self {
+            rng: Rng::new(config.seed),
+            config: WrapperConfig {
+                action_smoothing: config.action_smoothing.clamp(0.0, 0.99),
+                ..config
+            },
+            history: VecDeque::new(),
+            smoothed_da: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> WrapperConfig {
+        self.config
+    }
+
+    // Back to the seeded state, e.g. at the start of a race
+    pub fn reset(&mut self) {
+        *self = Self::new(self.config);
+    }
+
+    // Noise first, then the delay: the policy sees the noisy observation from
+    // latency_ticks calls ago (the oldest one until enough have been made).
+    pub fn observe(&mut self, input: &PolicyInput) -> PolicyInput {
+        let mut input = input.clone();
+        let (position_noise, velocity_noise) = (self.config.position_noise, self.config.velocity_noise);
+        if position_noise > 0.0 || velocity_noise > 0.0 {
+            // vertex_v rows are [px, py, vx, vy], see ObservationBuilder::build
+            for row in input.vertex_v.chunks_mut(4) {
+                for (i, value) in row.iter_mut().enumerate() {
+                    let std = if i < 2 { position_noise } else { velocity_noise };
+                    *value += std * self.rng.gaussian();
+                }
+            }
+        }
+
+        self.history.push_back(input);
+        while self.history.len() > self.config.latency_ticks + 1 {
+            self.history.pop_front();
+        }
+        self.history.front().unwrap().clone()
+    }
+
+    // Exponential moving average of da
+    pub fn filter(&mut self, da: &[f32]) -> Vec<f32> {
+        let alpha = self.config.action_smoothing;
+        if self.smoothed_da.len() != da.len() {
+            self.smoothed_da = da.to_vec();
+        } else {
+            for (s, &d) in self.smoothed_da.iter_mut().zip(da.iter()) {
+                *s = alpha * *s + (1.0 - alpha) * d;
+            }
+        }
+        self.smoothed_da.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(value: f32) -> PolicyInput {
+        PolicyInput {
+            vertex_k: vec![0.0; 4],
+            muscle_k: vec![0.0; 2],
+            vertex_v: vec![value; 8],
+            num_vertices: 2,
+            num_muscles: 1,
+        }
+    }
+
+    #[test]
+    fn test_noise_is_seeded() {
+        let config = WrapperConfig { position_noise: 0.1, velocity_noise: 0.5, seed: 1, ..WrapperConfig::default() };
+        let (mut a, mut b) = (PolicyWrappers::new(config), PolicyWrappers::new(config));
+        let (na, nb) = (a.observe(&input(0.0)), b.observe(&input(0.0)));
+        assert_eq!(na.vertex_v, nb.vertex_v);
+        assert!(na.vertex_v.iter().all(|&v| v != 0.0));
+        assert_eq!(na.vertex_k, input(0.0).vertex_k);
+
+        a.reset();
+        assert_eq!(a.observe(&input(0.0)).vertex_v, na.vertex_v);
+    }
+
+    #[test]
+    fn test_latency_delays_observations() {
+        let mut wrappers = PolicyWrappers::new(WrapperConfig { latency_ticks: 2, ..WrapperConfig::default() });
+        let seen: Vec<f32> = (0..5).map(|i| wrappers.observe(&input(i as f32)).vertex_v[0]).collect();
+        assert_eq!(seen, vec![0.0, 0.0, 0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_smoothing_low_passes_da() {
+        let mut wrappers = PolicyWrappers::new(WrapperConfig { action_smoothing: 0.5, ..WrapperConfig::default() });
+        assert_eq!(wrappers.filter(&[0.0]), vec![0.0]);
+        assert_eq!(wrappers.filter(&[1.0]), vec![0.5]);
+        assert_eq!(wrappers.filter(&[1.0]), vec![0.75]);
+
+        let mut passthrough = PolicyWrappers::default();
+        passthrough.filter(&[0.0]);
+        assert_eq!(passthrough.filter(&[1.0]), vec![1.0]);
+    }
+}
@@ -1,75 +1,166 @@
-mod components;
+pub mod components;
 
 use wasm_bindgen::prelude::*;
 pub use crate::components::state::GameState;
-use crate::components::creature::{Morphology, Creature};
+use crate::components::creature::Morphology;
 use crate::components::policy::AttentionModel;
-use crate::components::soft_body::SoftBodySimulation;
+use crate::components::quantized::{QuantizedAttentionModel, Quantization};
+use crate::components::cpg::{CpgController, Gait};
+use crate::components::controller::Controller;
+use crate::components::wrappers::{PolicyWrappers, WrapperConfig};
+use crate::components::clock::{TickClock, TICK_DT};
+use crate::components::bot::{Bot, Difficulty};
+use crate::components::input::{InputBinding, InputState, INPUT_STRIDE};
+use crate::components::mode::GameMode;
+use crate::components::phase::{RacePhase, FALSE_START_PENALTY, FALSE_START_THRESHOLD};
+use crate::components::racer::Racer;
+use crate::components::rope::Rope;
+use crate::components::rules::RaceRules;
+use crate::components::stamina::Stamina;
+use crate::components::track::Track;
+use crate::components::terrain::Terrain;
 
 #[wasm_bindgen]
 impl GameState {
     #[wasm_bindgen(constructor)]
     pub fn new_wasm(morphology: u8) -> GameState {
-        // This creates a new GameState for racing with two creatures
-        let mut state = GameState {
-            // Creature 1: top lane, facing right (0 degrees)
-            creature1_x: -10.0,
-            creature1_y: 1.0,
-            creature1_z: 2.0,  // Top lane
-            creature1_rotation_y: 0.0,
-            creature1_speed: 0.0,
-            
-            // Creature 2: bottom lane, facing right (0 degrees)
-            creature2_x: -10.0,
-            creature2_y: 1.0,
-            creature2_z: -2.0,  // Bottom lane
-            creature2_rotation_y: 0.0,
-            creature2_speed: 0.0,
-            
-            // Racing game state
-            game_started: false,
-            game_completed: false,
-            start_time: 0.0,
-            current_time: 0.0,
-            winner: 0,
-            
-            creature1: Creature::new(match morphology {
-                0 => Morphology::Biped,
-                1 => Morphology::Quadruped,
-                2 => Morphology::Hexapod,
-                _ => Morphology::Biped,
-            }),
-            creature2: Creature::new(match morphology {
-                0 => Morphology::Biped,
-                1 => Morphology::Quadruped,
-                2 => Morphology::Hexapod,
-                _ => Morphology::Biped,
-            }),
-            policy: None,
-            sim: None,
-        };
-        state
+        // Two creatures racing in the top and bottom lanes, facing right
+        GameState::new(GameMode::Race, Self::morphology_from_u8(morphology))
+    }
+
+    // 1 to 8 racers of the same morphology, one lane each
+    #[wasm_bindgen]
+    pub fn with_num_racers(morphology: u8, num_racers: usize) -> Result<GameState, JsValue> {
+        Self::new_with_mode(GameMode::Race, morphology, num_racers)
+    }
+
+    // Tug-of-war takes exactly 2 racers, the other modes 1 to 8
+    #[wasm_bindgen]
+    pub fn new_with_mode(mode: GameMode, morphology: u8, num_racers: usize) -> Result<GameState, JsValue> {
+        let morphology = Self::morphology_from_u8(morphology);
+        GameState::with_mode(mode, &vec![morphology; num_racers]).map_err(|e| JsValue::from_str(&e))
+    }
+
+    // One racer per morphology id, in lane order, e.g. [0, 1] for a biped
+    // against a quadruped
+    #[wasm_bindgen]
+    pub fn new_with_morphologies(mode: GameMode, morphologies: &[u8]) -> Result<GameState, JsValue> {
+        let morphologies = morphologies
+            .iter()
+            .map(|&id| Morphology::from_u8(id).ok_or_else(|| format!("unknown morphology {}", id)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| JsValue::from_str(&e))?;
+        GameState::with_mode(mode, &morphologies).map_err(|e| JsValue::from_str(&e))
     }
 
+    // Loads the same policy into every creature's slot
     #[wasm_bindgen]
     pub fn load_policy(&mut self, args_json: &str, weights_json: &str) {
-        let model = AttentionModel::new(args_json, weights_json);
-        self.policy = Some(model);
+        for racer in 0..self.racers.len() {
+            self.load_policy_for(racer, args_json, weights_json);
+        }
     }
 
     #[wasm_bindgen]
-    pub fn init_simulation(&mut self, mesh_json: &str) {
-        let sim = SoftBodySimulation::new(mesh_json);
-        self.sim = Some(sim);
+    pub fn load_policy_bin(&mut self, args_json: &str, weights: &[u8]) {
+        for racer in 0..self.racers.len() {
+            self.load_policy_bin_for(racer, args_json, weights);
+        }
     }
 
+    // Per-racer slots (0 = creature1, 1 = creature2, ...). These swap the
+    // controller mid-race without resetting anything; returns false if the
+    // racer does not exist or the policy failed to load.
     #[wasm_bindgen]
-    pub fn get_sim_node_positions(&self) -> JsValue {
-        if let Some(sim) = &self.sim {
-            let pos = sim.get_node_positions();
-            serde_wasm_bindgen::to_value(&pos).unwrap()
-        } else {
-            JsValue::NULL
+    pub fn load_policy_for(&mut self, racer: usize, args_json: &str, weights_json: &str) -> bool {
+        let model = AttentionModel::try_new(args_json, weights_json);
+        self.swap_or_fallback(racer, model.map(Controller::Attention), "load_policy")
+    }
+
+    #[wasm_bindgen]
+    pub fn load_policy_bin_for(&mut self, racer: usize, args_json: &str, weights: &[u8]) -> bool {
+        let model = AttentionModel::from_bin(args_json, weights);
+        self.swap_or_fallback(racer, model.map(Controller::Attention), "load_policy_bin")
+    }
+
+    #[wasm_bindgen]
+    pub fn load_quantized_policy_for(&mut self, racer: usize, args_json: &str, weights_json: &str, quantization: u8) -> bool {
+        let model = Quantization::from_u8(quantization)
+            .ok_or_else(|| format!("unknown quantization {}", quantization))
+            .and_then(|q| QuantizedAttentionModel::try_new(args_json, weights_json, q));
+        self.swap_or_fallback(racer, model.map(Controller::Quantized), "load_quantized_policy")
+    }
+
+    #[wasm_bindgen]
+    pub fn use_cpg(&mut self, gait: Gait) -> bool {
+        let mut ok = true;
+        for racer in 0..self.racers.len() {
+            ok &= self.use_cpg_for(racer, gait);
+        }
+        ok
+    }
+
+    #[wasm_bindgen]
+    pub fn use_cpg_for(&mut self, racer: usize, gait: Gait) -> bool {
+        let Some(racer) = self.racers.get_mut(racer) else {
+            return false;
+        };
+        match CpgController::new(racer.creature.morphology, gait) {
+            Ok(cpg) => {
+                racer.policy.swap(Controller::Cpg(cpg));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn set_cpg_params(&mut self, frequency: f32, amplitude: f32, phase: f32) {
+        for racer in &mut self.racers {
+            if let Some(Controller::Cpg(cpg)) = &mut racer.policy.controller {
+                cpg.set_params(frequency, amplitude, phase);
+            }
+        }
+    }
+
+    // Robustness experiments: observation noise (std dev of projected
+    // positions/velocities), observation delay in ticks and da smoothing
+    #[wasm_bindgen]
+    pub fn set_policy_wrappers(&mut self, racer: usize, position_noise: f32, velocity_noise: f32, latency_ticks: usize, action_smoothing: f32, seed: u64) -> bool {
+        let Some(racer) = self.racers.get_mut(racer) else {
+            return false;
+        };
+        racer.policy.wrappers = PolicyWrappers::new(WrapperConfig { position_noise, velocity_noise, latency_ticks, action_smoothing, seed });
+        true
+    }
+
+    // "attention", "quantized", "cpg" or "none"
+    #[wasm_bindgen]
+    pub fn get_policy_name(&self, racer: usize) -> String {
+        self.racers.get(racer).map_or("none", |r| r.policy.name()).to_string()
+    }
+
+    // Physics races: the soft body for every racer of this morphology
+    #[wasm_bindgen]
+    pub fn set_morphology_body(&mut self, morphology: u8, mesh_json: &str, policy_json: &str) -> bool {
+        let result = Morphology::from_u8(morphology)
+            .ok_or_else(|| format!("unknown morphology {}", morphology))
+            .and_then(|m| self.set_body(m, mesh_json, policy_json));
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                web_sys::console::warn_1(&format!("set_morphology_body failed ({})", err).into());
+                false
+            }
+        }
+    }
+
+    // A racer's soft-body node positions, or null without one
+    #[wasm_bindgen]
+    pub fn get_racer_node_positions(&self, index: usize) -> JsValue {
+        match self.racer(index).and_then(|r| r.body.as_ref()) {
+            Some(body) => serde_wasm_bindgen::to_value(&body.locomotion.sim.get_node_positions()).unwrap(),
+            None => JsValue::NULL,
         }
     }
 
@@ -77,104 +168,433 @@ impl GameState {
     pub fn start_game(&mut self, now: f64) {
         self.game_started = true;
         self.start_time = now;
+        self.clock = TickClock::start(now);
+        self.current_time = 0.0;
+        self.phases.start();
 
         self.winner = 0;
-    }
-
-    #[wasm_bindgen]
-    pub fn update(&mut self, delta: f32, now: f64, keys_json: &str) {
-        if !self.game_started || self.game_completed {
-            return;
+        for racer in &mut self.racers {
+            racer.policy.reset();
+            racer.penalty = 0.0;
+            racer.stats.false_start = false;
+            racer.stats.dnf = false;
+            racer.stamina = Stamina::default();
+            if let Some(bot) = &mut racer.bot {
+                bot.reset();
+            }
+        }
+        if let Some(rope) = &mut self.rope {
+            rope.reset();
         }
+        // Bodies start over from their rest pose
+        for racer in &mut self.racers {
+            racer.detach_body();
+        }
+        self.sync_bodies();
+        RaceRules::start(self);
+    }
 
-        self.current_time = (now - self.start_time) / 1000.0;
+    // The update methods run however many fixed ticks are due by host time
+    // `now`, in ms, all with the same input. `delta` is not used; it stays
+    // for the Python backend's signature.
 
-        // Parse JSON keys
+    // KeyboardEvent.code values held down, as a JSON array, read through
+    // each racer's binding
+    #[wasm_bindgen]
+    pub fn update(&mut self, _delta: f32, now: f64, keys_json: &str) {
         let keys: Vec<String> = match serde_json::from_str(keys_json) {
             Ok(keys) => keys,
-            Err(_) => return,
+            Err(err) => {
+                web_sys::console::warn_1(&format!("update: bad keys ({})", err).into());
+                Vec::new()
+            }
         };
+        let inputs: Vec<InputState> = self
+            .racers
+            .iter()
+            .map(|r| InputState::read(&r.binding, |code| keys.iter().any(|k| k == code), &[]))
+            .collect();
+        self.step_inputs(now, &inputs);
+    }
 
-        // Racing physics constants
-        let acceleration = 15.0;
-        let max_speed = 30.0;
-        let friction = 0.95;
-        let finish_line_x = 20.0;
+    // INPUT_STRIDE floats per racer in lane order: throttle and brake 0 to
+    // 1, steer -1 to 1, morph above 0.5. Missing racers get no input.
+    #[wasm_bindgen]
+    pub fn update_inputs(&mut self, _delta: f32, now: f64, inputs: &[f32]) {
+        let inputs: Vec<InputState> = inputs.chunks(INPUT_STRIDE).map(InputState::from_slice).collect();
+        self.step_inputs(now, &inputs);
+    }
 
-        // Player 1 controls (WASD)
-        let p1_accelerate = keys.iter().any(|k| k == "KeyW");
-        let p1_brake = keys.iter().any(|k| k == "KeyS");
-        
-        // Player 2 controls (Arrow keys)
-        let p2_accelerate = keys.iter().any(|k| k == "ArrowUp");
-        let p2_brake = keys.iter().any(|k| k == "ArrowDown");
+    // Raw devices through the bindings: one byte per get_bound_keys() entry,
+    // non-zero while held, and GAMEPAD_STRIDE floats per gamepad slot
+    // (standard-mapping button values, then axes)
+    #[wasm_bindgen]
+    pub fn update_devices(&mut self, _delta: f32, now: f64, keys: &[u8], gamepads: &[f32]) {
+        let bound = self.bound_keys();
+        let pressed = |code: &str| bound.iter().position(|k| k == code).is_some_and(|i| keys.get(i).is_some_and(|&k| k != 0));
+        let inputs: Vec<InputState> = self.racers.iter().map(|r| InputState::read(&r.binding, pressed, gamepads)).collect();
+        self.step_inputs(now, &inputs);
+    }
 
-        // Update creature 1 speed
-        if p1_accelerate {
-            self.creature1_speed += acceleration * delta;
-        } else if p1_brake {
-            self.creature1_speed -= acceleration * delta * 2.0;
-        }
-        self.creature1_speed = self.creature1_speed.clamp(0.0, max_speed);
-        self.creature1_speed *= friction;
+    // Every key code some racer is bound to, in the order update_devices
+    // expects them
+    #[wasm_bindgen]
+    pub fn get_bound_keys(&self) -> Vec<String> { self.bound_keys() }
 
-        // Update creature 2 speed
-        if p2_accelerate {
-            self.creature2_speed += acceleration * delta;
-        } else if p2_brake {
-            self.creature2_speed -= acceleration * delta * 2.0;
+    // A JSON array of bindings, one per racer in lane order; racers past
+    // the end keep theirs
+    #[wasm_bindgen]
+    pub fn load_bindings(&mut self, bindings_json: &str) -> bool {
+        match InputBinding::table_from_json(bindings_json) {
+            Ok(table) => {
+                for (racer, binding) in self.racers.iter_mut().zip(table) {
+                    racer.binding = binding;
+                }
+                true
+            }
+            Err(err) => {
+                web_sys::console::warn_1(&format!("load_bindings failed ({})", err).into());
+                false
+            }
         }
-        self.creature2_speed = self.creature2_speed.clamp(0.0, max_speed);
-        self.creature2_speed *= friction;
+    }
+
+    #[wasm_bindgen]
+    pub fn get_mode(&self) -> GameMode { self.mode }
 
-        // Update positions
-        self.creature1_x += self.creature1_speed * delta;
-        self.creature2_x += self.creature2_speed * delta;
+    // Tug-of-war rope marker x and tension in newtons; 0 in other modes
+    #[wasm_bindgen]
+    pub fn get_rope_marker(&self) -> f32 {
+        if self.rope.is_some() { Rope::marker(&self.racers) } else { 0.0 }
+    }
+    #[wasm_bindgen]
+    pub fn get_rope_tension(&self) -> f32 { self.rope.as_ref().map_or(0.0, |r| r.tension) }
 
-        // Check win conditions
-        if !self.game_completed {
-            if self.creature1_x > finish_line_x && self.creature2_x > finish_line_x {
-                self.game_completed = true;
-                self.winner = if self.creature1_x > self.creature2_x { 1 } else { 2 };
-            } else if self.creature1_x > finish_line_x {
-                self.game_completed = true;
-                self.winner = 1;
-            } else if self.creature2_x > finish_line_x {
-                self.game_completed = true;
-                self.winner = 2;
+    // data/tracks/*.json; open tracks for races and time trials, closed ones
+    // for laps. Racers move to the start of the new track.
+    #[wasm_bindgen]
+    pub fn load_track(&mut self, track_json: &str) -> bool {
+        match Track::from_json(track_json).and_then(|track| self.set_track(track)) {
+            Ok(()) => true,
+            Err(err) => {
+                web_sys::console::warn_1(&format!("load_track failed ({})", err).into());
+                false
             }
         }
     }
+    #[wasm_bindgen]
+    pub fn get_track_name(&self) -> String { self.track.name.clone() }
+    #[wasm_bindgen]
+    pub fn get_track_length(&self) -> f32 { self.track.length() }
+    // Centerline as x, z pairs
+    #[wasm_bindgen]
+    pub fn get_track_centerline(&self) -> Vec<f32> { self.track.centerline().iter().flatten().copied().collect() }
+    // Arc length of each checkpoint gate, then the finish
+    #[wasm_bindgen]
+    pub fn get_track_gates(&self) -> Vec<f32> { self.track.checkpoints.iter().chain([&self.track.finish]).copied().collect() }
+
+    // Lap races; takes effect from the next lap counted
+    #[wasm_bindgen]
+    pub fn set_total_laps(&mut self, total_laps: u32) -> bool {
+        if total_laps == 0 {
+            return false;
+        }
+        self.total_laps = total_laps;
+        true
+    }
+    #[wasm_bindgen]
+    pub fn get_total_laps(&self) -> u32 { self.total_laps }
+
+    #[wasm_bindgen]
+    pub fn num_racers(&self) -> usize { self.racers.len() }
+
+    // Racers by index; out-of-range indices read as 0
+    #[wasm_bindgen]
+    pub fn get_racer_x(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.x) }
+    #[wasm_bindgen]
+    pub fn get_racer_y(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.y) }
+    #[wasm_bindgen]
+    pub fn get_racer_z(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.z) }
+    #[wasm_bindgen]
+    pub fn get_racer_rotation_y(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.rotation_y) }
+    #[wasm_bindgen]
+    pub fn get_racer_speed(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.speed) }
+    // Derived from the racer's creature
+    #[wasm_bindgen]
+    pub fn get_racer_max_speed(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.dynamics.max_speed) }
+    #[wasm_bindgen]
+    pub fn get_racer_acceleration(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.dynamics.acceleration) }
+    // Current form; changes when a morph completes
+    #[wasm_bindgen]
+    pub fn get_morphology(&self, index: usize) -> Option<Morphology> { self.racer(index).map(|r| r.creature.morphology) }
+    #[wasm_bindgen]
+    pub fn get_racer_lane(&self, index: usize) -> usize { self.racer(index).map_or(0, |r| r.lane) }
+    #[wasm_bindgen]
+    pub fn get_racer_distance(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.stats.distance) }
+    #[wasm_bindgen]
+    pub fn get_racer_top_speed(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.stats.top_speed) }
+    #[wasm_bindgen]
+    pub fn get_racer_finish_time(&self, index: usize) -> Option<f64> { self.racer(index)?.stats.finish_time }
+    // Arc length along the track's centerline
+    #[wasm_bindgen]
+    pub fn get_racer_progress(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.laps.distance) }
+    // Checkpoints passed on the current lap
+    #[wasm_bindgen]
+    pub fn get_racer_checkpoint(&self, index: usize) -> usize { self.racer(index).map_or(0, |r| r.laps.checkpoint) }
+    #[wasm_bindgen]
+    pub fn get_racer_lap(&self, index: usize) -> u32 { self.racer(index).map_or(0, |r| r.laps.lap) }
+    // Seconds for each completed lap, in order
+    #[wasm_bindgen]
+    pub fn get_racer_lap_splits(&self, index: usize) -> Vec<f64> { self.racer(index).map_or(Vec::new(), |r| r.laps.splits.clone()) }
+    #[wasm_bindgen]
+    pub fn is_racer_wrong_way(&self, index: usize) -> bool { self.racer(index).is_some_and(|r| r.laps.wrong_way) }
+
+    // A bot drives the racer from now on and its binding is ignored, so one
+    // player can race the rest
+    #[wasm_bindgen]
+    pub fn set_racer_bot(&mut self, index: usize, difficulty: Difficulty) -> bool {
+        let Some(racer) = self.racer_mut(index) else {
+            return false;
+        };
+        racer.bot = Some(Bot::new(difficulty));
+        true
+    }
+    // Hands the racer back to its binding
+    #[wasm_bindgen]
+    pub fn clear_racer_bot(&mut self, index: usize) -> bool {
+        self.racer_mut(index).and_then(|r| r.bot.take()).is_some()
+    }
+    #[wasm_bindgen]
+    pub fn get_racer_bot(&self, index: usize) -> Option<Difficulty> { self.racer(index)?.bot.as_ref().map(|b| b.difficulty) }
+
+    // KeyboardEvent.code values
+    #[wasm_bindgen]
+    pub fn set_racer_binding(&mut self, index: usize, accelerate: &str, brake: &str) -> bool {
+        let Some(racer) = self.racer_mut(index) else {
+            return false;
+        };
+        racer.binding.throttle = accelerate.to_string();
+        racer.binding.brake = brake.to_string();
+        true
+    }
+    #[wasm_bindgen]
+    pub fn set_racer_morph_key(&mut self, index: usize, morph: &str) -> bool {
+        let Some(racer) = self.racer_mut(index) else {
+            return false;
+        };
+        racer.binding.morph = morph.to_string();
+        true
+    }
 
+    // Starts a change of form; false while morphing or cooling down
+    #[wasm_bindgen]
+    pub fn morph_racer(&mut self, index: usize, morphology: u8) -> bool {
+        let Some(morphology) = Morphology::from_u8(morphology) else {
+            return false;
+        };
+        self.racer_mut(index).is_some_and(|r| r.start_morph(morphology))
+    }
+    #[wasm_bindgen]
+    pub fn is_racer_morphing(&self, index: usize) -> bool { self.racer(index).is_some_and(|r| r.morph.target.is_some()) }
     #[wasm_bindgen]
-    pub fn get_creature1_x(&self) -> f32 { self.creature1_x }
+    pub fn get_racer_morph_cooldown(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.morph.cooldown) }
+    // 0 spent to 1 fresh
     #[wasm_bindgen]
-    pub fn get_creature1_y(&self) -> f32 { self.creature1_y }
+    pub fn get_racer_stamina(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.stamina.level) }
+    // Share of full muscle force stamina still allows
+    #[wasm_bindgen]
+    pub fn get_racer_force_cap(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.stamina.force_cap()) }
+    #[wasm_bindgen]
+    pub fn get_racer_terrain(&self, index: usize) -> Terrain { self.racer(index).map_or(Terrain::Flat, |r| r.terrain) }
+
+    // Two-player getters used by script.js
     #[wasm_bindgen]
-    pub fn get_creature1_z(&self) -> f32 { self.creature1_z }
+    pub fn get_creature1_x(&self) -> f32 { self.get_racer_x(0) }
     #[wasm_bindgen]
-    pub fn get_creature1_rotation_y(&self) -> f32 { self.creature1_rotation_y }
+    pub fn get_creature1_y(&self) -> f32 { self.get_racer_y(0) }
     #[wasm_bindgen]
-    pub fn get_creature1_speed(&self) -> f32 { self.creature1_speed }
+    pub fn get_creature1_z(&self) -> f32 { self.get_racer_z(0) }
+    #[wasm_bindgen]
+    pub fn get_creature1_rotation_y(&self) -> f32 { self.get_racer_rotation_y(0) }
+    #[wasm_bindgen]
+    pub fn get_creature1_speed(&self) -> f32 { self.get_racer_speed(0) }
     
     #[wasm_bindgen]
-    pub fn get_creature2_x(&self) -> f32 { self.creature2_x }
+    pub fn get_creature2_x(&self) -> f32 { self.get_racer_x(1) }
     #[wasm_bindgen]
-    pub fn get_creature2_y(&self) -> f32 { self.creature2_y }
+    pub fn get_creature2_y(&self) -> f32 { self.get_racer_y(1) }
     #[wasm_bindgen]
-    pub fn get_creature2_z(&self) -> f32 { self.creature2_z }
+    pub fn get_creature2_z(&self) -> f32 { self.get_racer_z(1) }
     #[wasm_bindgen]
-    pub fn get_creature2_rotation_y(&self) -> f32 { self.creature2_rotation_y }
+    pub fn get_creature2_rotation_y(&self) -> f32 { self.get_racer_rotation_y(1) }
     #[wasm_bindgen]
-    pub fn get_creature2_speed(&self) -> f32 { self.creature2_speed }
+    pub fn get_creature2_speed(&self) -> f32 { self.get_racer_speed(1) }
     
 
     #[wasm_bindgen]
     pub fn is_completed(&self) -> bool { self.game_completed }
     #[wasm_bindgen]
     pub fn get_winner(&self) -> i32 { self.winner }
+    // Whether the winner shares first place
+    #[wasm_bindgen]
+    pub fn is_dead_heat(&self) -> bool { self.game_completed && self.standings().iter().filter(|&&(_, place)| place == 1).count() > 1 }
+    // Racer indices, best first: finishers by time, then by progress
+    #[wasm_bindgen]
+    pub fn get_finishing_order(&self) -> Vec<u32> { self.standings().iter().map(|&(index, _)| index as u32).collect() }
+    // From 1, shared on a dead heat; 0 for a DNF or no such racer
+    #[wasm_bindgen]
+    pub fn get_racer_place(&self, index: usize) -> u32 {
+        self.standings().iter().find(|&&(i, _)| i == index).map_or(0, |&(_, place)| place)
+    }
     #[wasm_bindgen]
     pub fn get_current_time(&self) -> f64 { self.current_time }
+    #[wasm_bindgen]
+    pub fn get_current_tick(&self) -> u64 { self.clock.tick }
 
+    // Lights before the go, from the next start_game
+    #[wasm_bindgen]
+    pub fn set_countdown(&mut self, seconds: f32) -> bool {
+        match self.phases.set_countdown(seconds) {
+            Ok(()) => true,
+            Err(err) => {
+                web_sys::console::warn_1(&format!("set_countdown failed ({})", err).into());
+                false
+            }
+        }
+    }
+    #[wasm_bindgen]
+    pub fn get_race_phase(&self) -> RacePhase { self.phases.phase }
+    // Seconds left before the go, 0 outside the countdown
+    #[wasm_bindgen]
+    pub fn get_countdown_remaining(&self) -> f32 { self.phases.countdown_seconds() }
+    #[wasm_bindgen]
+    pub fn get_racer_phase(&self, index: usize) -> RacePhase { self.racer(index).map_or(RacePhase::Lobby, |r| self.phases.racer_phase(r)) }
+    // Not over the line when the race was called
+    #[wasm_bindgen]
+    pub fn get_racer_dnf(&self, index: usize) -> bool { self.racer(index).is_some_and(|r| r.stats.dnf) }
+    #[wasm_bindgen]
+    pub fn get_racer_false_start(&self, index: usize) -> bool { self.racer(index).is_some_and(|r| r.stats.false_start) }
+    // Seconds the racer is still held at the line
+    #[wasm_bindgen]
+    pub fn get_racer_penalty(&self, index: usize) -> f32 { self.racer(index).map_or(0.0, |r| r.penalty) }
+}
 
+impl GameState {
+    // Every tick due by host time `now`, each with the same inputs
+    pub fn step_inputs(&mut self, now: f64, inputs: &[InputState]) {
+        if !self.phases.is_running() {
+            return;
+        }
+        for _ in 0..self.clock.advance(now) {
+            self.tick(inputs);
+        }
+    }
+
+    // One fixed tick with every racer's input. Given the same inputs tick by
+    // tick, a race plays out the same whatever the host's frame timing.
+    pub fn tick(&mut self, inputs: &[InputState]) {
+        if !self.phases.is_running() {
+            return;
+        }
+        let delta = TICK_DT;
+        self.clock.tick += 1;
+
+        // Nobody moves before the go; whoever opens the throttle anyway is
+        // held at the line once it comes
+        if self.phases.phase == RacePhase::Countdown {
+            for (i, racer) in self.racers.iter_mut().enumerate() {
+                let input = inputs.get(i).copied().unwrap_or_default();
+                if racer.bot.is_none() && input.throttle > FALSE_START_THRESHOLD && !racer.stats.false_start {
+                    racer.stats.false_start = true;
+                    racer.penalty = FALSE_START_PENALTY;
+                }
+            }
+            self.phases.count_down(self.clock.tick);
+            return;
+        }
+        self.current_time = self.phases.race_time(self.clock.tick);
+
+        // Outside physics races there is no per-creature soft body to
+        // observe, so attention policies hold their activations and only the
+        // CPG moves. Racers that just morphed get their new body first.
+        self.sync_bodies();
+        let remaining: Vec<f32> = self.racers.iter().map(|r| self.remaining(r)).collect();
+        for (i, racer) in self.racers.iter_mut().enumerate() {
+            // Over the line, a racer eases off while the rest come in
+            if racer.stats.finish_time.is_some() {
+                racer.drive(delta, &InputState::default());
+                continue;
+            }
+            let mut input = inputs.get(i).copied().unwrap_or_default();
+            if let Some(mut bot) = racer.bot.take() {
+                let action = bot.decide(racer, &self.track, remaining[i]);
+                input = action.input;
+                if let Some(morphology) = action.morph {
+                    racer.start_morph(morphology);
+                }
+                racer.bot = Some(bot);
+            }
+            racer.drive(delta, &input);
+        }
+        self.mode.step(&mut self.racers, self.rope.as_mut(), &self.track, delta);
+        RaceRules::update(self);
+
+        // The race goes on until everyone is in or time is called on them
+        if self.mode.is_over(&self.racers, self.current_time) {
+            for racer in &mut self.racers {
+                racer.stats.dnf = self.mode != GameMode::TugOfWar && racer.stats.finish_time.is_none();
+            }
+            self.game_completed = true;
+            self.phases.phase = RacePhase::Results;
+            self.winner = self.mode.winner(&self.racers).map_or(0, |index| index as i32 + 1);
+        }
+    }
+
+    // How far the racer still has to go to finish, along the centerline
+    pub fn remaining(&self, racer: &Racer) -> f32 {
+        if self.mode == GameMode::TugOfWar {
+            f32::INFINITY
+        } else if self.track.closed {
+            (self.total_laps as f32 - racer.laps.progress()).max(0.0) * self.track.length()
+        } else {
+            (self.track.finish - racer.stats.distance).max(0.0)
+        }
+    }
+
+    pub fn standings(&self) -> Vec<(usize, u32)> {
+        self.mode.standings(&self.racers)
+    }
+
+    fn bound_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+        for code in self.racers.iter().flat_map(|r| r.binding.keys()) {
+            if !code.is_empty() && !keys.iter().any(|k| k == code) {
+                keys.push(code.to_string());
+            }
+        }
+        keys
+    }
+
+    // The original constructors fall back to bipeds for unknown ids
+    fn morphology_from_u8(morphology: u8) -> Morphology {
+        Morphology::from_u8(morphology).unwrap_or(Morphology::Biped)
+    }
+
+    fn swap_or_fallback(&mut self, racer: usize, controller: Result<Controller, String>, what: &str) -> bool {
+        let Some(racer) = self.racers.get_mut(racer) else {
+            return false;
+        };
+        match controller {
+            Ok(controller) => {
+                racer.policy.swap(controller);
+                true
+            }
+            Err(err) => {
+                // Fall back to a gait that works without trained weights
+                web_sys::console::warn_1(&format!("{} failed ({}), using CPG gait", what, err).into());
+                racer.policy.swap(Controller::Cpg(CpgController::for_morphology(racer.creature.morphology)));
+                false
+            }
+        }
+    }
 }
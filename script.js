@@ -18,6 +18,43 @@ let uiState = {
 // Input handling
 let keys = {};
 
+// GameMode id of the soft-body race (rust/components/mode.rs)
+const PHYSICS_RACE = 4;
+
+// Per-gamepad layout of update_devices (rust/components/input.rs):
+// standard-mapping button values, then axes
+const GAMEPAD_BUTTONS = 17;
+const GAMEPAD_AXES = 4;
+const GAMEPAD_STRIDE = GAMEPAD_BUTTONS + GAMEPAD_AXES;
+
+// One byte per key in get_bound_keys() order, and GAMEPAD_STRIDE floats per
+// navigator.getGamepads() slot
+function readDevices() {
+    const boundKeys = gameLogic.get_bound_keys();
+    const pressed = new Uint8Array(boundKeys.length);
+    boundKeys.forEach((code, i) => {
+        pressed[i] = keys[code] ? 1 : 0;
+    });
+
+    const gamepads = navigator.getGamepads ? Array.from(navigator.getGamepads()) : [];
+    const pads = new Float32Array(gamepads.length * GAMEPAD_STRIDE);
+    gamepads.forEach((pad, slot) => {
+        if (!pad) return;
+        const base = slot * GAMEPAD_STRIDE;
+        pad.buttons.slice(0, GAMEPAD_BUTTONS).forEach((button, i) => {
+            pads[base + i] = button.value;
+        });
+        pad.axes.slice(0, GAMEPAD_AXES).forEach((axis, i) => {
+            pads[base + GAMEPAD_BUTTONS + i] = axis;
+        });
+    });
+    return { pressed, pads };
+}
+
+function morphIndex(type) {
+    return type === 'Biped' ? 0 : (type === 'Quadruped' ? 1 : 2);
+}
+
 // Initialize the game
 function init() {
     console.log("Initializing game...");
@@ -25,8 +62,7 @@ function init() {
     if (window.pyodide && window.GameState) {
         console.log("Using Python (Pyodide) backend");
         try {
-            const morphIdx = uiState.morphType === 'Biped' ? 0 : (uiState.morphType === 'Quadruped' ? 1 : 2);
-            gameLogic = window.GameState(morphIdx);
+            gameLogic = window.GameState(morphIndex(uiState.morphType));
             isPythonBackend = true;
             console.log("Python GameState instance created");
         } catch (e) {
@@ -39,8 +75,7 @@ function init() {
                 gameLogic = window.gameStateInstance;
                 console.log("Using pre-initialized Rust GameState with policy");
             } else {
-                const morphIdx = uiState.morphType === 'Biped' ? 0 : (uiState.morphType === 'Quadruped' ? 1 : 2);
-                gameLogic = new window.GameState(morphIdx);
+                gameLogic = window.GameState.new_with_mode(PHYSICS_RACE, morphIndex(uiState.morphType), 2);
             }
             isPythonBackend = false;
             console.log("Rust GameState instance created");
@@ -179,17 +214,20 @@ function createSimpleCreature(color) {
 }
 
 async function loadCreatureMesh(type) {
-    const path = `data/agents/${type.toLowerCase()}/mesh.json`;
+    const dir = `data/agents/${type.toLowerCase()}`;
     try {
-        const response = await fetch(path);
+        const response = await fetch(`${dir}/mesh.json`);
         const meshJsonText = await response.text();
         creatureMeshData = JSON.parse(meshJsonText);
         console.log(`Loaded mesh for ${type}`);
-        
-        // Initialize Rust simulation with this mesh
-        if (gameLogic && !isPythonBackend) {
-            gameLogic.init_simulation(meshJsonText);
-            console.log("Rust SoftBody simulation initialized");
+
+        // Physics races run the creatures as soft bodies under the agent's policy
+        if (!isPythonBackend && gameLogic.set_morphology_body) {
+            const policyResponse = await fetch(`${dir}/policy.json`);
+            const policyJsonText = await policyResponse.text();
+            if (!gameLogic.set_morphology_body(morphIndex(type), meshJsonText, policyJsonText)) {
+                console.warn(`No soft body for ${type}; showing the mesh at rest`);
+            }
         }
 
         // Replace current player morph with complex one
@@ -332,12 +370,9 @@ function startRace() {
 
 
 function restartGame() {
-    const morphIdx = uiState.morphType === 'Biped' ? 0 : (uiState.morphType === 'Quadruped' ? 1 : 2);
-    
+    // start_game resets the Rust state but keeps its policy and soft bodies
     if (isPythonBackend) {
-        gameLogic = window.GameState(morphIdx);
-    } else {
-        gameLogic = new window.GameState(morphIdx);
+        gameLogic = window.GameState(morphIndex(uiState.morphType));
     }
     
     // Reset positions
@@ -356,22 +391,29 @@ function restartGame() {
 
 function updateGameState() {
     const delta = clock.getDelta();
-    const activeKeys = Object.keys(keys).filter(k => keys[k]);
-    
-    gameLogic.update(delta, Date.now(), activeKeys);
+    if (isPythonBackend) {
+        const activeKeys = Object.keys(keys).filter(k => keys[k]);
+        gameLogic.update(delta, Date.now(), activeKeys);
+    } else {
+        // Raw devices; the racers' bindings in the backend turn them into input
+        const { pressed, pads } = readDevices();
+        gameLogic.update_devices(delta, Date.now(), pressed, pads);
+    }
 
-    // Sync from Rust simulation if available
-    if (!isPythonBackend && gameLogic.get_sim_node_positions) {
-        const nodePositions = gameLogic.get_sim_node_positions();
+    // Sync from the player's soft body in physics races
+    if (!isPythonBackend && gameLogic.get_racer_node_positions) {
+        const nodePositions = gameLogic.get_racer_node_positions(0);
         if (nodePositions && nodeMeshes.length > 0) {
+            // The group already follows the racer, so center the nodes on it
+            const centerX = nodePositions.reduce((sum, pos) => sum + pos[0], 0) / nodePositions.length;
             nodePositions.forEach((pos, i) => {
                 if (nodeMeshes[i]) {
-                    nodeMeshes[i].position.set(pos[0], pos[1], 0);
+                    nodeMeshes[i].position.set(pos[0] - centerX, pos[1], 0);
                 }
             });
 
             // Update body mesh and edges
-            const mesh = playerMorph.children[0];
+            const mesh = playerMorph1.children[0];
             const positions = mesh.geometry.attributes.position.array;
             nodeMeshes.forEach((node, i) => {
                 positions[i * 3] = node.position.x;
@@ -402,8 +444,11 @@ function updateGameState() {
         playerMorph2.rotation.y = gameLogic.get_creature2_rotation_y();
     }
     
-    // Update timer UI
-    document.getElementById('timer').textContent = gameLogic.get_current_time().toFixed(2) + 's';
+    // Update timer UI, counting down to the go first
+    const countdown = gameLogic.get_countdown_remaining ? gameLogic.get_countdown_remaining() : 0;
+    document.getElementById('timer').textContent = countdown > 0
+        ? Math.ceil(countdown).toString()
+        : gameLogic.get_current_time().toFixed(2) + 's';
     
     // Check for winner
     if (gameLogic.is_completed()) {
@@ -411,7 +456,10 @@ function updateGameState() {
         if (winner > 0) {
             document.getElementById('endScreen').style.display = 'flex';
             document.getElementById('finalTime').textContent = gameLogic.get_current_time().toFixed(2) + 's';
-            document.getElementById('winnerText').textContent = winner === 1 ? "Player 1 Wins!" : "Player 2 Wins!";
+            const deadHeat = gameLogic.is_dead_heat ? gameLogic.is_dead_heat() : false;
+            document.getElementById('winnerText').textContent = deadHeat
+                ? "Dead Heat!"
+                : (winner === 1 ? "Player 1 Wins!" : "Player 2 Wins!");
         }
     }
 